@@ -79,7 +79,7 @@ fn main() {
     // let shadow_sphere = shapes::Sphere::new_boxed(Some(transform), Some(material));
 
     // Light
-    let light = PointLight::new(Vec4::point(-10.0, 10.0, -10.0), Color::WHITE);
+    let light = PointLight::new_boxed(Vec4::point(-10.0, 10.0, -10.0), Color::WHITE);
     // // let light2 = PointLight::new(Vec4::point(10.0, 10.0, -10.0), Color::rgb(0.5, 0.5, 0.5));
 
     // world
@@ -96,12 +96,14 @@ fn main() {
     // w.add_light(light2);
 
     // Camera
-    let mut camera = Camera::new(1920, 1080, PI / 3.0);
-    camera.view_transform(
+    let camera = Camera::look_at(
         &Vec4::point(1.0, 5.0, -6.0),
         &Vec4::point(0.0, 1.0, 0.0),
         &Vec4::VEC_Y_ONE,
-    );
+    )
+    .expect("Valid camera placement")
+    .fov(PI / 3.0)
+    .resolution(1920, 1080);
 
     // Render
     let canvas = render(&camera, &w, &RenderSettings::default());
@@ -0,0 +1,308 @@
+//! Axis aligned bounding boxes
+//!
+
+use crate::math::matrix::Mat4;
+use crate::math::vec4::Vec4;
+use crate::math::EPSILON;
+use crate::ray::Ray;
+
+/// Axis aligned box given by its minimum and maximum corners.
+/// Extents may be infinite, e.g. for planes.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BoundingBox {
+    pub min: Vec4,
+    pub max: Vec4,
+}
+
+impl BoundingBox {
+    /// Box containing nothing, adding a point to it gives a box of that point
+    pub const EMPTY: Self = Self {
+        min: Vec4 {
+            x: f64::INFINITY,
+            y: f64::INFINITY,
+            z: f64::INFINITY,
+            w: 1.0,
+        },
+        max: Vec4 {
+            x: -f64::INFINITY,
+            y: -f64::INFINITY,
+            z: -f64::INFINITY,
+            w: 1.0,
+        },
+    };
+
+    pub fn new(min: Vec4, max: Vec4) -> Self {
+        Self { min, max }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.min.x > self.max.x || self.min.y > self.max.y || self.min.z > self.max.z
+    }
+
+    pub fn is_infinite(&self) -> bool {
+        [
+            self.min.x, self.min.y, self.min.z, self.max.x, self.max.y, self.max.z,
+        ]
+        .iter()
+        .any(|c| c.is_infinite())
+    }
+
+    pub fn add_point(&mut self, p: &Vec4) {
+        self.min.x = self.min.x.min(p.x);
+        self.min.y = self.min.y.min(p.y);
+        self.min.z = self.min.z.min(p.z);
+        self.max.x = self.max.x.max(p.x);
+        self.max.y = self.max.y.max(p.y);
+        self.max.z = self.max.z.max(p.z);
+    }
+
+    pub fn merge(&mut self, other: &BoundingBox) {
+        if other.is_empty() {
+            return;
+        }
+        self.add_point(&other.min);
+        self.add_point(&other.max);
+    }
+
+    pub fn contains_point(&self, p: &Vec4) -> bool {
+        (self.min.x..=self.max.x).contains(&p.x)
+            && (self.min.y..=self.max.y).contains(&p.y)
+            && (self.min.z..=self.max.z).contains(&p.z)
+    }
+
+    pub fn contains_box(&self, other: &BoundingBox) -> bool {
+        self.contains_point(&other.min) && self.contains_point(&other.max)
+    }
+
+    /// Bounds of this box after transformation by `m`.
+    /// Computed per axis (Arvo's method) so that infinite extents
+    /// don't turn into NaN when multiplied by zero matrix entries.
+    pub fn transform(&self, m: &Mat4) -> Self {
+        if self.is_empty() {
+            return *self;
+        }
+        let min = [self.min.x, self.min.y, self.min.z];
+        let max = [self.max.x, self.max.y, self.max.z];
+        let mut new_min = [m.get(0, 3), m.get(1, 3), m.get(2, 3)];
+        let mut new_max = new_min;
+        for i in 0..3 {
+            for j in 0..3 {
+                let f = m.get(i, j);
+                if f == 0.0 {
+                    continue;
+                }
+                let a = f * min[j];
+                let b = f * max[j];
+                new_min[i] += a.min(b);
+                new_max[i] += a.max(b);
+            }
+        }
+        Self {
+            min: Vec4::point(new_min[0], new_min[1], new_min[2]),
+            max: Vec4::point(new_max[0], new_max[1], new_max[2]),
+        }
+    }
+
+    /// Splits the box in half along its longest axis
+    pub fn split(&self) -> (Self, Self) {
+        let dx = self.max.x - self.min.x;
+        let dy = self.max.y - self.min.y;
+        let dz = self.max.z - self.min.z;
+        let greatest = dx.max(dy).max(dz);
+
+        let mut mid_min = self.min;
+        let mut mid_max = self.max;
+        if greatest == dx {
+            mid_min.x = self.min.x + dx / 2.0;
+            mid_max.x = mid_min.x;
+        } else if greatest == dy {
+            mid_min.y = self.min.y + dy / 2.0;
+            mid_max.y = mid_min.y;
+        } else {
+            mid_min.z = self.min.z + dz / 2.0;
+            mid_max.z = mid_min.z;
+        }
+        (Self::new(self.min, mid_max), Self::new(mid_min, self.max))
+    }
+
+    /// Slab test, only tells whether the ray passes through the box
+    pub fn intersects(&self, ray: &Ray) -> bool {
+        let (mut tmin, mut tmax) =
+            Self::check_axis(ray.origin.x, ray.direction.x, self.min.x, self.max.x);
+        let (cmin, cmax) = Self::check_axis(ray.origin.y, ray.direction.y, self.min.y, self.max.y);
+        tmin = tmin.max(cmin);
+        tmax = tmax.min(cmax);
+        let (cmin, cmax) = Self::check_axis(ray.origin.z, ray.direction.z, self.min.z, self.max.z);
+        tmin = tmin.max(cmin);
+        tmax = tmax.min(cmax);
+        tmin <= tmax
+    }
+
+    fn check_axis(origin: f64, direction: f64, min: f64, max: f64) -> (f64, f64) {
+        let tmin_num = min - origin;
+        let tmax_num = max - origin;
+
+        let (tmin, tmax) = if direction.abs() >= EPSILON {
+            (tmin_num / direction, tmax_num / direction)
+        } else {
+            (tmin_num * f64::INFINITY, tmax_num * f64::INFINITY)
+        };
+        if tmin > tmax {
+            (tmax, tmin)
+        } else {
+            (tmin, tmax)
+        }
+    }
+}
+
+impl Default for BoundingBox {
+    fn default() -> Self {
+        Self::EMPTY
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use itertools::izip;
+    use std::f64::consts::PI;
+
+    #[test]
+    fn basic() {
+        let b = BoundingBox::default();
+        assert!(b.is_empty());
+        let b = BoundingBox::new(Vec4::point(-1.0, -2.0, -3.0), Vec4::point(3.0, 2.0, 1.0));
+        assert!(!b.is_empty());
+        assert_eq!(b.min, Vec4::point(-1.0, -2.0, -3.0));
+        assert_eq!(b.max, Vec4::point(3.0, 2.0, 1.0));
+    }
+
+    #[test]
+    fn add_points() {
+        let mut b = BoundingBox::default();
+        b.add_point(&Vec4::point(-5.0, 2.0, 0.0));
+        b.add_point(&Vec4::point(7.0, 0.0, -3.0));
+        assert_eq!(b.min, Vec4::point(-5.0, 0.0, -3.0));
+        assert_eq!(b.max, Vec4::point(7.0, 2.0, 0.0));
+    }
+
+    #[test]
+    fn merge() {
+        let mut b = BoundingBox::new(Vec4::point(-5.0, -2.0, 0.0), Vec4::point(7.0, 4.0, 4.0));
+        b.merge(&BoundingBox::new(
+            Vec4::point(8.0, -7.0, -2.0),
+            Vec4::point(14.0, 2.0, 8.0),
+        ));
+        b.merge(&BoundingBox::EMPTY);
+        assert_eq!(b.min, Vec4::point(-5.0, -7.0, -2.0));
+        assert_eq!(b.max, Vec4::point(14.0, 4.0, 8.0));
+    }
+
+    #[test]
+    fn contains() {
+        let b = BoundingBox::new(Vec4::point(5.0, -2.0, 0.0), Vec4::point(11.0, 4.0, 7.0));
+        let points = [
+            Vec4::point(5.0, -2.0, 0.0),
+            Vec4::point(11.0, 4.0, 7.0),
+            Vec4::point(8.0, 1.0, 3.0),
+            Vec4::point(3.0, 0.0, 3.0),
+            Vec4::point(8.0, -4.0, 3.0),
+            Vec4::point(8.0, 1.0, 8.0),
+        ];
+        let exps = [true, true, true, false, false, false];
+        for (p, e) in izip!(&points, &exps) {
+            assert_eq!(b.contains_point(p), *e, "Failed for {:?}", p);
+        }
+
+        let inner = BoundingBox::new(Vec4::point(6.0, -1.0, 1.0), Vec4::point(10.0, 3.0, 6.0));
+        let overlapping =
+            BoundingBox::new(Vec4::point(4.0, -3.0, -1.0), Vec4::point(10.0, 3.0, 6.0));
+        assert!(b.contains_box(&inner));
+        assert!(!b.contains_box(&overlapping));
+    }
+
+    #[test]
+    fn transform() {
+        let b = BoundingBox::new(Vec4::point(-1.0, -1.0, -1.0), Vec4::point(1.0, 1.0, 1.0));
+        let m = Mat4::rotation_x(PI / 4.0) * Mat4::rotation_y(PI / 4.0);
+        let t = b.transform(&m);
+        assert_eq!(t.min, Vec4::point(-1.41421, -1.70711, -1.70711));
+        assert_eq!(t.max, Vec4::point(1.41421, 1.70711, 1.70711));
+    }
+
+    #[test]
+    fn transform_infinite() {
+        let b = BoundingBox::new(
+            Vec4::point(-f64::INFINITY, 0.0, -f64::INFINITY),
+            Vec4::point(f64::INFINITY, 0.0, f64::INFINITY),
+        );
+        let t = b.transform(&Mat4::translation(0.0, 2.0, 0.0));
+        assert_eq!(t.min.y, 2.0);
+        assert_eq!(t.max.y, 2.0);
+        assert!(t.min.x.is_infinite() && t.min.x < 0.0);
+        assert!(t.max.z.is_infinite());
+        assert!(t.is_infinite());
+    }
+
+    #[test]
+    fn split() {
+        let b = BoundingBox::new(Vec4::point(-1.0, -2.0, -3.0), Vec4::point(9.0, 5.5, 3.0));
+        let (left, right) = b.split();
+        assert_eq!(left.min, Vec4::point(-1.0, -2.0, -3.0));
+        assert_eq!(left.max, Vec4::point(4.0, 5.5, 3.0));
+        assert_eq!(right.min, Vec4::point(4.0, -2.0, -3.0));
+        assert_eq!(right.max, Vec4::point(9.0, 5.5, 3.0));
+
+        let b = BoundingBox::new(Vec4::point(-1.0, -2.0, -3.0), Vec4::point(5.0, 8.0, 3.0));
+        let (left, right) = b.split();
+        assert_eq!(left.max, Vec4::point(5.0, 3.0, 3.0));
+        assert_eq!(right.min, Vec4::point(-1.0, 3.0, -3.0));
+
+        let b = BoundingBox::new(Vec4::point(-1.0, -2.0, -3.0), Vec4::point(5.0, 3.0, 7.0));
+        let (left, right) = b.split();
+        assert_eq!(left.max, Vec4::point(5.0, 3.0, 2.0));
+        assert_eq!(right.min, Vec4::point(-1.0, -2.0, 2.0));
+    }
+
+    #[test]
+    fn ray_intersects() {
+        let b = BoundingBox::new(Vec4::point(5.0, -2.0, 0.0), Vec4::point(11.0, 4.0, 7.0));
+        let orgs = [
+            Vec4::point(15.0, 1.0, 2.0),
+            Vec4::point(-5.0, -1.0, 4.0),
+            Vec4::point(7.0, 6.0, 5.0),
+            Vec4::point(9.0, -5.0, 6.0),
+            Vec4::point(8.0, 2.0, 12.0),
+            Vec4::point(6.0, 0.0, -5.0),
+            Vec4::point(8.0, 1.0, 3.5),
+            Vec4::point(9.0, -1.0, -8.0),
+            Vec4::point(8.0, 3.0, -4.0),
+            Vec4::point(9.0, -1.0, -2.0),
+            Vec4::point(4.0, 0.0, 9.0),
+            Vec4::point(8.0, 6.0, -1.0),
+            Vec4::point(12.0, 5.0, 4.0),
+        ];
+        let dirs = [
+            -Vec4::VEC_X_ONE,
+            Vec4::VEC_X_ONE,
+            -Vec4::VEC_Y_ONE,
+            Vec4::VEC_Y_ONE,
+            -Vec4::VEC_Z_ONE,
+            Vec4::VEC_Z_ONE,
+            Vec4::VEC_Z_ONE,
+            Vec4::vec(2.0, 4.0, 6.0),
+            Vec4::vec(6.0, 2.0, 4.0),
+            Vec4::vec(4.0, 6.0, 2.0),
+            -Vec4::VEC_Z_ONE,
+            -Vec4::VEC_Y_ONE,
+            -Vec4::VEC_X_ONE,
+        ];
+        let exps = [
+            true, true, true, true, true, true, true, false, false, false, false, false, false,
+        ];
+        for (o, d, e) in izip!(&orgs, &dirs, &exps) {
+            let r = Ray::new(o, &d.normalize());
+            assert_eq!(b.intersects(&r), *e, "Failed for {:?} {:?}", o, d);
+        }
+    }
+}
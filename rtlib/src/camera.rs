@@ -2,34 +2,148 @@ use crate::math::matrix::Mat4;
 use crate::math::vec4::Vec4;
 use crate::ray::Ray;
 
+use std::f64::consts::PI;
+
+/// How rays leave the camera
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum Projection {
+    /// Rays spread from a single point over the camera's field of view
+    #[default]
+    Perspective,
+    /// Parallel rays covering a `width` x `height` area in world units,
+    /// objects keep their size regardless of the distance
+    Orthographic { width: f64, height: f64 },
+    /// Full 360° x 180° panorama, longitude along the image width and
+    /// latitude along its height, e.g. for environment maps
+    Equirectangular,
+    /// Equidistant fisheye, the angle from the view axis grows linearly with
+    /// the distance from the image center. `fov` (up to `2 * PI`) is covered
+    /// by the circle touching the shorter sides of the image, pixels outside
+    /// of it continue the mapping up to straight behind the camera.
+    Fisheye { fov: f64 },
+}
+
+/// Pinhole camera by default. Giving it an aperture turns it into a thin
+/// lens, where only objects at the focal distance are sharp.
+///
+/// Can be set up in one expression, e.g.
+/// `Camera::look_at(&from, &to, &up)?.fov(PI / 3.0).resolution(1920, 1080)`.
 pub struct Camera {
     height: u32,
     width: u32,
     fov: f64,
+    projection: Projection,
     transform: Mat4,
+    inverse_transform: Mat4,
     pixel_size: f64,
     half_height: f64,
     half_width: f64,
+    aperture: f64,
+    focal_distance: f64,
+    shutter_open: f64,
+    shutter_close: f64,
 }
 
 impl Camera {
     pub fn new(width: u32, height: u32, fov: f64) -> Self {
-        let half_view = (fov / 2.0).tan();
-        let aspect = width as f64 / height as f64;
+        let mut ret = Self {
+            height,
+            width,
+            fov,
+            projection: Projection::Perspective,
+            transform: Mat4::IDENTITY,
+            inverse_transform: Mat4::IDENTITY,
+            pixel_size: 0.0,
+            half_height: 0.0,
+            half_width: 0.0,
+            aperture: 0.0,
+            focal_distance: 1.0,
+            shutter_open: 0.0,
+            shutter_close: 0.0,
+        };
+        ret.update_view();
+        ret
+    }
+
+    /// 100x100 camera at `from` looking at `to`, with a field of view of `PI / 3`.
+    /// Start of a chain, e.g. `Camera::look_at(&from, &to, &up)?.resolution(640, 480)`.
+    /// Fails like `view_transform`.
+    pub fn look_at(from: &Vec4, to: &Vec4, up: &Vec4) -> Result<Self, &'static str> {
+        let mut ret = Self::new(100, 100, PI / 3.0);
+        ret.view_transform(from, to, up)?;
+        Ok(ret)
+    }
+
+    pub fn fov(mut self, fov: f64) -> Self {
+        self.fov = fov;
+        self.update_view();
+        self
+    }
+
+    pub fn resolution(mut self, width: u32, height: u32) -> Self {
+        self.width = width;
+        self.height = height;
+        self.update_view();
+        self
+    }
+
+    pub fn projection(mut self, projection: Projection) -> Self {
+        self.set_projection(projection);
+        self
+    }
+
+    pub fn aperture(mut self, aperture: f64) -> Self {
+        self.set_aperture(aperture);
+        self
+    }
+
+    pub fn focal_distance(mut self, focal_distance: f64) -> Self {
+        self.set_focal_distance(focal_distance);
+        self
+    }
+
+    pub fn shutter(mut self, open: f64, close: f64) -> Self {
+        self.set_shutter(open, close);
+        self
+    }
+
+    /// Size of the image plane and its pixels for the current resolution and fov
+    fn update_view(&mut self) {
+        let half_view = (self.fov / 2.0).tan();
+        let aspect = self.width as f64 / self.height as f64;
         let (half_width, half_height) = if aspect < 1.0 {
             (half_view * aspect, half_view)
         } else {
             (half_view, half_view / aspect)
         };
-        Self {
-            height,
-            width,
-            fov,
-            transform: Mat4::IDENTITY,
-            pixel_size: (half_width * 2.0) / width as f64,
-            half_height,
-            half_width,
-        }
+        self.half_width = half_width;
+        self.half_height = half_height;
+        self.pixel_size = (half_width * 2.0) / self.width as f64;
+    }
+
+    /// Camera with parallel rays covering `view_width` x `view_height`
+    /// world units around its axis
+    pub fn orthographic(width: u32, height: u32, view_width: f64, view_height: f64) -> Self {
+        let mut ret = Self::new(width, height, PI / 2.0);
+        ret.projection = Projection::Orthographic {
+            width: view_width,
+            height: view_height,
+        };
+        ret
+    }
+
+    /// Camera seeing everything around it, see [`Projection::Equirectangular`]
+    pub fn equirectangular(width: u32, height: u32) -> Self {
+        let mut ret = Self::new(width, height, PI / 2.0);
+        ret.projection = Projection::Equirectangular;
+        ret
+    }
+
+    /// Fisheye camera, see [`Projection::Fisheye`]
+    pub fn fisheye(width: u32, height: u32, fov: f64) -> Self {
+        let mut ret = Self::new(width, height, PI / 2.0);
+        ret.projection = Projection::Fisheye { fov };
+        ret
     }
 
     pub fn get_width(&self) -> u32 {
@@ -40,37 +154,184 @@ impl Camera {
         self.height
     }
 
-    pub fn view_transform(&mut self, from: &Vec4, to: &Vec4, up: &Vec4) {
-        self.transform = Mat4::view_transform(from, to, up);
+    pub fn get_projection(&self) -> Projection {
+        self.projection
     }
 
-    pub fn ray_for_pixel(&self, x: u32, y: u32) -> Ray {
-        let x_offset = (x as f64 + 0.5) * self.pixel_size;
-        let y_offset = (y as f64 + 0.5) * self.pixel_size;
+    pub fn set_projection(&mut self, projection: Projection) {
+        self.projection = projection;
+    }
 
-        let world_x = self.half_width - x_offset;
-        let world_y = self.half_height - y_offset;
+    pub fn get_aperture(&self) -> f64 {
+        self.aperture
+    }
+
+    /// Diameter of the lens, `0.0` makes a pinhole camera
+    pub fn set_aperture(&mut self, aperture: f64) {
+        self.aperture = aperture.max(0.0);
+    }
+
+    pub fn get_focal_distance(&self) -> f64 {
+        self.focal_distance
+    }
+
+    /// Distance from the camera to the plane in perfect focus.
+    /// Measured along the ray for the panoramic projections.
+    pub fn set_focal_distance(&mut self, focal_distance: f64) {
+        self.focal_distance = focal_distance;
+    }
+
+    pub fn get_shutter(&self) -> (f64, f64) {
+        (self.shutter_open, self.shutter_close)
+    }
+
+    /// Time interval the rays are cast in. Moving shapes blur over it
+    /// when more samples per pixel are taken, `open == close` freezes them.
+    pub fn set_shutter(&mut self, open: f64, close: f64) {
+        self.shutter_open = open;
+        self.shutter_close = close.max(open);
+    }
+
+    /// Time within the shutter interval, `s` in `[0, 1)`
+    pub fn time_at(&self, s: f64) -> f64 {
+        self.shutter_open + (self.shutter_close - self.shutter_open) * s
+    }
 
-        let pixel = self
-            .transform
-            .inverse()
-            .expect("Camera transform inverse matrix")
-            * Vec4::point(world_x, world_y, -1.0);
-        let origin = self
-            .transform
-            .inverse()
-            .expect("Camera transform inverse matrix")
-            * Vec4::POINT_ZERO;
-        let direction = (pixel - origin).normalize();
-        Ray::new(&origin, &direction)
+    pub fn get_fov(&self) -> f64 {
+        self.fov
     }
+
+    pub fn get_transform(&self) -> &Mat4 {
+        &self.transform
+    }
+
+    /// Sets the world to camera transform, fails if it can't be inverted
+    pub fn set_transform(&mut self, transform: Mat4) -> Result<(), &'static str> {
+        self.inverse_transform = transform.inverse()?;
+        self.transform = transform;
+        Ok(())
+    }
+
+    pub fn view_transform(
+        &mut self,
+        from: &Vec4,
+        to: &Vec4,
+        up: &Vec4,
+    ) -> Result<(), &'static str> {
+        let forward = to - from;
+        if forward.magnitude() == 0.0 {
+            return Err("Camera looks at its own position");
+        }
+        if forward.cross(up).magnitude() == 0.0 {
+            return Err("Up is parallel to the view direction");
+        }
+        self.set_transform(Mat4::view_transform(from, to, up))
+    }
+
+    /// Ray through the pixel at `x`, `y`.
+    /// `offset_x` and `offset_y` pick the point within the pixel,
+    /// both in `[0, 1)` with `0.5` being its center.
+    /// The ray leaves from the center of the lens when the shutter opens.
+    pub fn ray_for_pixel(&self, x: u32, y: u32, offset_x: f64, offset_y: f64) -> Ray {
+        self.ray_through_lens(x, y, offset_x, offset_y, 0.5, 0.5)
+    }
+
+    /// Same as `ray_for_pixel`, but leaving the lens at `lens_u`, `lens_v`.
+    /// Both are in `[0, 1)` and mapped onto the lens disk, `0.5` being its center.
+    /// Rays through the same point of a pixel meet at the focal distance.
+    pub fn ray_through_lens(
+        &self,
+        x: u32,
+        y: u32,
+        offset_x: f64,
+        offset_y: f64,
+        lens_u: f64,
+        lens_v: f64,
+    ) -> Ray {
+        // pinhole ray in camera space, from `lens` through `target` at z = -1
+        let (lens, target) = match self.projection {
+            Projection::Perspective => {
+                let x_offset = (x as f64 + offset_x) * self.pixel_size;
+                let y_offset = (y as f64 + offset_y) * self.pixel_size;
+
+                let world_x = self.half_width - x_offset;
+                let world_y = self.half_height - y_offset;
+                (Vec4::POINT_ZERO, Vec4::point(world_x, world_y, -1.0))
+            }
+            Projection::Orthographic { width, height } => {
+                let world_x = width * (0.5 - (x as f64 + offset_x) / self.width as f64);
+                let world_y = height * (0.5 - (y as f64 + offset_y) / self.height as f64);
+                (
+                    Vec4::point(world_x, world_y, 0.0),
+                    Vec4::point(world_x, world_y, -1.0),
+                )
+            }
+            Projection::Equirectangular => {
+                let longitude = 2.0 * PI * (0.5 - (x as f64 + offset_x) / self.width as f64);
+                let latitude = PI * (0.5 - (y as f64 + offset_y) / self.height as f64);
+                let direction = Vec4::vec(
+                    latitude.cos() * longitude.sin(),
+                    latitude.sin(),
+                    -latitude.cos() * longitude.cos(),
+                );
+                (Vec4::POINT_ZERO, Vec4::POINT_ZERO + direction)
+            }
+            Projection::Fisheye { fov } => {
+                let radius = self.width.min(self.height) as f64 / 2.0;
+                let nx = (self.width as f64 / 2.0 - (x as f64 + offset_x)) / radius;
+                let ny = (self.height as f64 / 2.0 - (y as f64 + offset_y)) / radius;
+                let r = (nx * nx + ny * ny).sqrt();
+                let theta = (r * fov / 2.0).min(PI);
+                let direction = if r > 0.0 {
+                    let s = theta.sin() / r;
+                    Vec4::vec(nx * s, ny * s, -theta.cos())
+                } else {
+                    -Vec4::VEC_Z_ONE
+                };
+                (Vec4::POINT_ZERO, Vec4::POINT_ZERO + direction)
+            }
+        };
+
+        // thin lens, moves the start over the lens keeping the point in focus.
+        // Panoramic targets are a unit away, so they focus on a sphere.
+        let (lens, target) = if self.aperture > 0.0 {
+            let (lx, ly) = concentric_disk(lens_u, lens_v);
+            let radius = self.aperture / 2.0;
+            (
+                lens + Vec4::vec(lx * radius, ly * radius, 0.0),
+                lens + (target - lens) * self.focal_distance,
+            )
+        } else {
+            (lens, target)
+        };
+
+        let origin = self.inverse_transform * lens;
+        let direction = (self.inverse_transform * target - origin).normalize();
+        Ray::new_with_time(&origin, &direction, self.shutter_open)
+    }
+}
+
+/// Maps the unit square onto the unit disk (Shirley and Chiu),
+/// keeping evenly spread samples evenly spread
+fn concentric_disk(u: f64, v: f64) -> (f64, f64) {
+    let a = 2.0 * u - 1.0;
+    let b = 2.0 * v - 1.0;
+    if a == 0.0 && b == 0.0 {
+        return (0.0, 0.0);
+    }
+    let (r, theta) = if a.abs() > b.abs() {
+        (a, PI / 4.0 * (b / a))
+    } else {
+        (b, PI / 2.0 - PI / 4.0 * (a / b))
+    };
+    (r * theta.cos(), r * theta.sin())
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::math::EPSILON;
-    use std::f64::consts::PI;
+    use crate::math::{EPSILON, SQRT_2};
+    use itertools::izip;
 
     #[test]
     fn basic() {
@@ -91,20 +352,279 @@ mod tests {
     fn ray_for_pixel() {
         let mut camera = Camera::new(201, 101, PI / 2.0);
 
-        let r = camera.ray_for_pixel(100, 50);
+        let r = camera.ray_for_pixel(100, 50, 0.5, 0.5);
         assert_eq!(r.origin, Vec4::POINT_ZERO);
         assert_eq!(r.direction, -Vec4::VEC_Z_ONE);
 
-        let r = camera.ray_for_pixel(0, 0);
+        let r = camera.ray_for_pixel(0, 0, 0.5, 0.5);
         assert_eq!(r.origin, Vec4::POINT_ZERO);
         assert_eq!(r.direction, Vec4::vec(0.66519, 0.33259, -0.66851));
 
-        camera.transform = Mat4::rotation_y(PI / 4.0) * Mat4::translation(0.0, -2.0, 5.0);
-        let r = camera.ray_for_pixel(100, 50);
+        camera
+            .set_transform(Mat4::rotation_y(PI / 4.0) * Mat4::translation(0.0, -2.0, 5.0))
+            .unwrap();
+        let r = camera.ray_for_pixel(100, 50, 0.5, 0.5);
         assert_eq!(r.origin, Vec4::point(0.0, 2.0, -5.0));
         assert_eq!(
             r.direction,
             Vec4::vec(2f64.sqrt() / 2.0, 0.0, -2f64.sqrt() / 2.0)
         );
     }
+
+    #[test]
+    fn ray_for_pixel_offset() {
+        let camera = Camera::new(2, 2, PI / 2.0);
+        let r = camera.ray_for_pixel(1, 1, 0.0, 0.0);
+        assert_eq!(r.direction, -Vec4::VEC_Z_ONE);
+        let r = camera.ray_for_pixel(0, 0, 0.0, 0.0);
+        assert_eq!(r.direction, Vec4::vec(1.0, 1.0, -1.0).normalize());
+    }
+
+    #[test]
+    fn concentric_disk() {
+        let squares = [(0.5, 0.5), (1.0, 0.5), (0.5, 0.0), (1.0, 1.0), (0.0, 0.0)];
+        let exps = [
+            (0.0, 0.0),
+            (1.0, 0.0),
+            (0.0, -1.0),
+            (SQRT_2 / 2.0, SQRT_2 / 2.0),
+            (-SQRT_2 / 2.0, -SQRT_2 / 2.0),
+        ];
+        for ((u, v), (ex, ey)) in izip!(&squares, &exps) {
+            let (x, y) = super::concentric_disk(*u, *v);
+            assert!((x - ex).abs() < EPSILON, "Failed for {} {}", u, v);
+            assert!((y - ey).abs() < EPSILON, "Failed for {} {}", u, v);
+        }
+        for i in 0..10 {
+            for j in 0..10 {
+                let (x, y) = super::concentric_disk(i as f64 / 10.0, j as f64 / 10.0);
+                assert!(x * x + y * y <= 1.0 + EPSILON);
+            }
+        }
+    }
+
+    #[test]
+    fn thin_lens() {
+        let mut camera = Camera::new(201, 101, PI / 2.0);
+        camera
+            .set_transform(Mat4::translation(0.0, 0.0, -5.0))
+            .unwrap();
+        let pinhole = camera.ray_for_pixel(20, 30, 0.5, 0.5);
+        let r = camera.ray_through_lens(20, 30, 0.5, 0.5, 0.1, 0.9);
+        assert_eq!(r.origin, pinhole.origin);
+        assert_eq!(r.direction, pinhole.direction);
+
+        camera.set_aperture(0.5);
+        camera.set_focal_distance(4.0);
+        assert_eq!(camera.get_aperture(), 0.5);
+        assert_eq!(camera.get_focal_distance(), 4.0);
+        let center = camera.ray_for_pixel(100, 50, 0.5, 0.5);
+        assert_eq!(center.origin, Vec4::point(0.0, 0.0, 5.0));
+        assert_eq!(center.direction, -Vec4::VEC_Z_ONE);
+
+        // every ray through the pixel center meets on the focal plane
+        let focus = Vec4::point(0.0, 0.0, 1.0);
+        let lens = [(0.0, 0.0), (1.0, 0.5), (0.25, 0.75), (0.9, 0.1)];
+        for (u, v) in lens.iter() {
+            let r = camera.ray_through_lens(100, 50, 0.5, 0.5, *u, *v);
+            assert!((r.origin - Vec4::point(0.0, 0.0, 5.0)).magnitude() <= 0.25 + EPSILON);
+            assert_eq!(r.origin.z, 5.0);
+            let t = (r.origin.z - 1.0) / -r.direction.z;
+            assert_eq!(r.position(t), focus, "Failed for {} {}", u, v);
+        }
+        let r = camera.ray_through_lens(100, 50, 0.5, 0.5, 1.0, 0.5);
+        assert_eq!(r.origin, Vec4::point(0.25, 0.0, 5.0));
+    }
+
+    #[test]
+    fn orthographic() {
+        let mut camera = Camera::orthographic(100, 50, 10.0, 5.0);
+        assert_eq!(
+            camera.get_projection(),
+            Projection::Orthographic {
+                width: 10.0,
+                height: 5.0
+            }
+        );
+        let pixels = [(50, 25, 0.0, 0.0), (0, 0, 0.5, 0.5), (99, 49, 1.0, 1.0)];
+        let exps = [
+            Vec4::POINT_ZERO,
+            Vec4::point(4.95, 2.45, 0.0),
+            Vec4::point(-5.0, -2.5, 0.0),
+        ];
+        for ((x, y, ox, oy), e) in izip!(&pixels, &exps) {
+            let r = camera.ray_for_pixel(*x, *y, *ox, *oy);
+            assert_eq!(r.origin, *e);
+            assert_eq!(r.direction, -Vec4::VEC_Z_ONE);
+        }
+
+        camera
+            .set_transform(Mat4::rotation_y(PI / 2.0) * Mat4::translation(0.0, -2.0, 5.0))
+            .unwrap();
+        let r = camera.ray_for_pixel(0, 0, 0.5, 0.5);
+        assert_eq!(r.origin, Vec4::point(0.0, 4.45, -0.05));
+        assert_eq!(r.direction, Vec4::VEC_X_ONE);
+
+        // thin lens rays meet on the focal plane in front of their pixel
+        camera.set_transform(Mat4::IDENTITY).unwrap();
+        camera.set_aperture(1.0);
+        camera.set_focal_distance(3.0);
+        for (u, v) in [(0.0, 0.0), (1.0, 0.5), (0.3, 0.8)] {
+            let r = camera.ray_through_lens(0, 0, 0.5, 0.5, u, v);
+            let t = -3.0 / r.direction.z;
+            assert_eq!(r.position(t), Vec4::point(4.95, 2.45, -3.0));
+        }
+    }
+
+    fn assert_directions(camera: &Camera, pixels: &[(u32, u32)], exps: &[Vec4]) {
+        for ((x, y), e) in izip!(pixels, exps) {
+            let r = camera.ray_for_pixel(*x, *y, 0.0, 0.0);
+            assert_eq!(r.origin, Vec4::POINT_ZERO);
+            assert_eq!(r.direction, *e, "Failed for {} {}", x, y);
+            assert!((r.direction.magnitude() - 1.0).abs() < EPSILON);
+        }
+    }
+
+    #[test]
+    fn equirectangular() {
+        let camera = Camera::equirectangular(360, 180);
+        assert_eq!(camera.get_projection(), Projection::Equirectangular);
+        let pixels = [(180, 90), (90, 90), (270, 90), (0, 90), (180, 0), (180, 45)];
+        let exps = [
+            -Vec4::VEC_Z_ONE,
+            Vec4::VEC_X_ONE,
+            -Vec4::VEC_X_ONE,
+            Vec4::VEC_Z_ONE,
+            Vec4::VEC_Y_ONE,
+            Vec4::vec(0.0, SQRT_2 / 2.0, -SQRT_2 / 2.0),
+        ];
+        assert_directions(&camera, &pixels, &exps);
+    }
+
+    #[test]
+    fn fisheye() {
+        let camera = Camera::fisheye(100, 100, PI);
+        assert_eq!(camera.get_projection(), Projection::Fisheye { fov: PI });
+        let pixels = [(50, 50), (0, 50), (50, 0), (25, 50), (100, 50)];
+        let exps = [
+            -Vec4::VEC_Z_ONE,
+            Vec4::VEC_X_ONE,
+            Vec4::VEC_Y_ONE,
+            Vec4::vec(SQRT_2 / 2.0, 0.0, -SQRT_2 / 2.0),
+            -Vec4::VEC_X_ONE,
+        ];
+        assert_directions(&camera, &pixels, &exps);
+
+        // wider image, the circle spans its height
+        let camera = Camera::fisheye(200, 100, 2.0 * PI);
+        let pixels = [(100, 50), (50, 50), (100, 0), (0, 50)];
+        let exps = [
+            -Vec4::VEC_Z_ONE,
+            Vec4::VEC_Z_ONE,
+            Vec4::VEC_Z_ONE,
+            Vec4::VEC_Z_ONE,
+        ];
+        assert_directions(&camera, &pixels, &exps);
+    }
+
+    #[test]
+    fn set_transform() {
+        let mut camera = Camera::new(11, 11, PI / 2.0);
+        let m = Mat4::translation(1.0, 2.0, 3.0);
+        assert!(camera.set_transform(m).is_ok());
+        assert_eq!(camera.get_transform(), &m);
+        assert_eq!(
+            camera.inverse_transform,
+            Mat4::translation(-1.0, -2.0, -3.0)
+        );
+        assert_eq!(
+            camera.set_transform(Mat4::scaling(1.0, 0.0, 1.0)),
+            Err("Matrix is not invertible")
+        );
+        // the camera is left as it was
+        assert_eq!(camera.get_transform(), &m);
+        let r = camera.ray_for_pixel(5, 5, 0.5, 0.5);
+        assert_eq!(r.origin, Vec4::point(-1.0, -2.0, -3.0));
+    }
+
+    #[test]
+    fn builder() {
+        let from = Vec4::point(0.0, 0.0, -5.0);
+        let to = Vec4::POINT_ZERO;
+        let up = Vec4::VEC_Y_ONE;
+        let camera = Camera::look_at(&from, &to, &up).unwrap();
+        assert_eq!(camera.get_width(), 100);
+        assert_eq!(camera.get_height(), 100);
+        assert_eq!(camera.get_fov(), PI / 3.0);
+        assert_eq!(
+            camera.get_transform(),
+            &Mat4::view_transform(&from, &to, &up)
+        );
+
+        let camera = Camera::look_at(&from, &to, &up)
+            .unwrap()
+            .fov(PI / 2.0)
+            .resolution(201, 101)
+            .aperture(0.1)
+            .focal_distance(5.0);
+        let mut exp = Camera::new(201, 101, PI / 2.0);
+        exp.view_transform(&from, &to, &up).unwrap();
+        assert_eq!(camera.get_fov(), PI / 2.0);
+        assert_eq!(camera.pixel_size, exp.pixel_size);
+        assert_eq!(camera.half_width, exp.half_width);
+        assert_eq!(camera.half_height, exp.half_height);
+        assert_eq!(camera.get_aperture(), 0.1);
+        assert_eq!(camera.get_focal_distance(), 5.0);
+        let r = camera.ray_for_pixel(0, 0, 0.5, 0.5);
+        let e = exp.ray_for_pixel(0, 0, 0.5, 0.5);
+        assert_eq!(r.origin, e.origin);
+        assert_eq!(r.direction, e.direction);
+
+        let camera = Camera::look_at(&from, &to, &up)
+            .unwrap()
+            .projection(Projection::Equirectangular);
+        assert_eq!(camera.get_projection(), Projection::Equirectangular);
+    }
+
+    #[test]
+    fn degenerate_look_at() {
+        let from = Vec4::point(0.0, 0.0, -5.0);
+        let ups = [Vec4::VEC_Y_ONE, Vec4::VEC_Z_ONE, Vec4::ZERO];
+        let tos = [from, Vec4::POINT_ZERO, Vec4::POINT_ZERO];
+        for (to, up) in izip!(&tos, &ups) {
+            assert!(Camera::look_at(&from, to, up).is_err(), "{:?} {:?}", to, up);
+        }
+
+        // the camera is left as it was
+        let mut camera = Camera::look_at(&from, &Vec4::POINT_ZERO, &Vec4::VEC_Y_ONE).unwrap();
+        let m = *camera.get_transform();
+        assert_eq!(
+            camera.view_transform(&from, &from, &Vec4::VEC_Y_ONE),
+            Err("Camera looks at its own position")
+        );
+        assert_eq!(camera.get_transform(), &m);
+    }
+
+    #[test]
+    fn shutter() {
+        let mut camera = Camera::new(11, 11, PI / 2.0);
+        assert_eq!(camera.get_shutter(), (0.0, 0.0));
+        assert_eq!(camera.ray_for_pixel(5, 5, 0.5, 0.5).time, 0.0);
+        camera.set_shutter(0.5, 1.5);
+        assert_eq!(camera.get_shutter(), (0.5, 1.5));
+        assert_eq!(camera.time_at(0.0), 0.5);
+        assert_eq!(camera.time_at(0.25), 0.75);
+        assert_eq!(camera.ray_for_pixel(5, 5, 0.5, 0.5).time, 0.5);
+        camera.set_shutter(2.0, 1.0);
+        assert_eq!(camera.get_shutter(), (2.0, 2.0));
+
+        let camera = Camera::look_at(
+            &Vec4::POINT_ZERO,
+            &Vec4::point(0.0, 0.0, -1.0),
+            &Vec4::VEC_Y_ONE,
+        )
+        .unwrap()
+        .shutter(0.0, 1.0);
+        assert_eq!(camera.get_shutter(), (0.0, 1.0));
+    }
 }
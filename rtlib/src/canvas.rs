@@ -16,6 +16,14 @@ impl Canvas {
         }
     }
 
+    pub fn get_width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn get_height(&self) -> u32 {
+        self.height
+    }
+
     fn in_bounds(&self, x: u32, y: u32) -> Result<(), &'static str> {
         if x >= self.width || y >= self.height {
             Err("Point out of canvas bounds")
@@ -12,11 +12,23 @@ use std::ops::Index;
 pub struct Intersection {
     pub object: BoxShape,
     pub t: f64,
+    /// Barycentric coordinates of the hit, only meaningful for triangles
+    pub u: f64,
+    pub v: f64,
 }
 
 impl Intersection {
     pub fn new(object: BoxShape, t: f64) -> Self {
-        Self { object, t }
+        Self {
+            object,
+            t,
+            u: 0.0,
+            v: 0.0,
+        }
+    }
+
+    pub fn new_with_uv(object: BoxShape, t: f64, u: f64, v: f64) -> Self {
+        Self { object, t, u, v }
     }
 
     pub fn precomputed(&self, ray: &Ray, xs: Option<&Vec<Intersection>>) -> PreCompute {
@@ -86,6 +98,12 @@ impl Intersections {
         self.inner.clear()
     }
 
+    /// Keeps only the intersections `f` returns true for.
+    /// Visits them in order, so `f` may track state along the ray.
+    pub fn retain<F: FnMut(&Intersection) -> bool>(&mut self, f: F) {
+        self.inner.retain(f);
+    }
+
     pub fn append(&mut self, other: &mut Self) {
         self.inner.append(&mut other.inner);
     }
@@ -93,6 +111,10 @@ impl Intersections {
     pub fn get_inner_ref(&self) -> &Vec<Intersection> {
         &self.inner
     }
+
+    pub fn iter_mut(&mut self) -> std::slice::IterMut<'_, Intersection> {
+        self.inner.iter_mut()
+    }
 }
 
 #[cfg(test)]
@@ -108,6 +130,16 @@ mod tests {
         assert_eq!(i.t, 3.5);
     }
 
+    #[test]
+    fn inter_with_uv() {
+        let s = Sphere::default_boxed();
+        let i = Intersection::new_with_uv(s.clone(), 3.5, 0.2, 0.4);
+        assert_eq!(&i.object, &s);
+        assert_eq!(i.t, 3.5);
+        assert_eq!(i.u, 0.2);
+        assert_eq!(i.v, 0.4);
+    }
+
     #[test]
     fn basic_inters() {
         let s0 = Sphere::default_boxed();
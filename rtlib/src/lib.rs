@@ -1,5 +1,6 @@
 pub mod prelude;
 
+pub mod bounds;
 pub mod camera;
 pub mod canvas;
 pub mod color;
@@ -7,6 +8,8 @@ pub mod intersection;
 pub mod light;
 pub mod material;
 pub mod math;
+pub mod obj;
+pub mod path_tracer;
 pub mod patterns;
 pub mod precompute;
 pub mod ray;
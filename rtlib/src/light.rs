@@ -1,10 +1,104 @@
+//! Light sources
+//!
+
 use crate::color::Color;
 use crate::math::vec4::Vec4;
+use crate::util::rng::Rng;
+
+use std::fmt::Debug;
+
+/// Where the light comes from, as seen from a point being shaded.
+/// Also the target of the shadow ray for that sample.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LightSample {
+    /// Unit vector from the point towards the light
+    pub direction: Vec4,
+    /// Distance to the light, infinite for directional lights
+    pub distance: f64,
+}
+
+impl LightSample {
+    /// Sample of a light located at `position`
+    pub fn towards(p: &Vec4, position: &Vec4) -> Self {
+        let to_light = position - p;
+        Self {
+            direction: to_light.normalize(),
+            distance: to_light.magnitude(),
+        }
+    }
+}
+
+/// How the light fades with distance `d`.
+/// Factors never exceed `1.0`, so a light's intensity is what
+/// surfaces right next to it receive.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum Attenuation {
+    /// Same intensity at any distance
+    #[default]
+    None,
+    /// `1 / (1 + k * d)`
+    Linear(f64),
+    /// `1 / (1 + k * d^2)`
+    Quadratic(f64),
+    /// Physically based falloff of a light with the given radius,
+    /// `(radius / d)^2`. The intensity is the one at the light's surface.
+    InverseSquare { radius: f64 },
+}
+
+impl Attenuation {
+    pub fn factor(&self, distance: f64) -> f64 {
+        match *self {
+            Self::None => 1.0,
+            Self::Linear(k) => 1.0 / (1.0 + k * distance),
+            Self::Quadratic(k) => 1.0 / (1.0 + k * distance * distance),
+            Self::InverseSquare { radius } => {
+                let ratio = radius / distance.max(radius);
+                ratio * ratio
+            }
+        }
+    }
+}
+
+pub trait Light: Debug + Send + Sync {
+    /// Color and brightness of the light
+    fn get_intensity(&self) -> Color;
+
+    /// Samples of the light as seen from `p`, shading averages over them
+    fn samples(&self, p: &Vec4) -> Vec<LightSample>;
+
+    /// Fraction of the light's intensity that reaches `p`,
+    /// ignoring anything in the way
+    fn intensity_at(&self, _p: &Vec4) -> f64 {
+        1.0
+    }
+
+    /// Applied per sample, using its distance
+    fn attenuation(&self) -> Attenuation {
+        Attenuation::None
+    }
+}
+
+pub type BoxLight = Box<dyn Light>;
 
 #[derive(Debug)]
 pub struct PointLight {
     pub position: Vec4,
     pub intensity: Color,
+    pub attenuation: Attenuation,
+}
+
+impl Light for PointLight {
+    fn get_intensity(&self) -> Color {
+        self.intensity
+    }
+
+    fn samples(&self, p: &Vec4) -> Vec<LightSample> {
+        vec![LightSample::towards(p, &self.position)]
+    }
+
+    fn attenuation(&self) -> Attenuation {
+        self.attenuation
+    }
 }
 
 impl PointLight {
@@ -12,8 +106,17 @@ impl PointLight {
         Self {
             intensity,
             position,
+            attenuation: Attenuation::None,
         }
     }
+
+    pub fn new_boxed(position: Vec4, intensity: Color) -> BoxLight {
+        Box::new(Self::new(position, intensity))
+    }
+
+    pub fn default_boxed() -> BoxLight {
+        Box::new(Self::default())
+    }
 }
 
 impl Default for PointLight {
@@ -21,13 +124,238 @@ impl Default for PointLight {
         Self {
             intensity: Color::rgb(1.0, 1.0, 1.0),
             position: Vec4::POINT_ZERO,
+            attenuation: Attenuation::None,
+        }
+    }
+}
+
+/// Light infinitely far away, e.g. the sun.
+/// Its rays are parallel and it lights every point the same.
+#[derive(Debug)]
+pub struct DirectionalLight {
+    /// Direction the light travels in
+    pub direction: Vec4,
+    pub intensity: Color,
+}
+
+impl Light for DirectionalLight {
+    fn get_intensity(&self) -> Color {
+        self.intensity
+    }
+
+    fn samples(&self, _p: &Vec4) -> Vec<LightSample> {
+        vec![LightSample {
+            direction: -self.direction,
+            distance: f64::INFINITY,
+        }]
+    }
+}
+
+impl DirectionalLight {
+    pub fn new(direction: Vec4, intensity: Color) -> Self {
+        Self {
+            direction: direction.normalize(),
+            intensity,
+        }
+    }
+
+    pub fn new_boxed(direction: Vec4, intensity: Color) -> BoxLight {
+        Box::new(Self::new(direction, intensity))
+    }
+}
+
+/// Point light shining only into a cone.
+/// Full intensity within `inner_angle` of its direction, fading out
+/// towards `outer_angle`. `falloff` shapes the fade, `1.0` being linear
+/// in the cosine of the angle and higher values giving a sharper rim.
+#[derive(Debug)]
+pub struct SpotLight {
+    pub position: Vec4,
+    pub direction: Vec4,
+    pub inner_angle: f64,
+    pub outer_angle: f64,
+    pub falloff: f64,
+    pub intensity: Color,
+    pub attenuation: Attenuation,
+}
+
+impl Light for SpotLight {
+    fn get_intensity(&self) -> Color {
+        self.intensity
+    }
+
+    fn samples(&self, p: &Vec4) -> Vec<LightSample> {
+        vec![LightSample::towards(p, &self.position)]
+    }
+
+    fn intensity_at(&self, p: &Vec4) -> f64 {
+        let cos_angle = (*p - self.position).normalize().dot(&self.direction);
+        let cos_inner = self.inner_angle.cos();
+        let cos_outer = self.outer_angle.cos();
+        if cos_angle >= cos_inner {
+            1.0
+        } else if cos_angle <= cos_outer {
+            0.0
+        } else {
+            ((cos_angle - cos_outer) / (cos_inner - cos_outer)).powf(self.falloff)
+        }
+    }
+
+    fn attenuation(&self) -> Attenuation {
+        self.attenuation
+    }
+}
+
+impl SpotLight {
+    /// Angles are measured from `direction` to the edge of the cone, in radians
+    pub fn new(
+        position: Vec4,
+        direction: Vec4,
+        inner_angle: f64,
+        outer_angle: f64,
+        falloff: f64,
+        intensity: Color,
+    ) -> Self {
+        Self {
+            position,
+            direction: direction.normalize(),
+            inner_angle,
+            outer_angle: outer_angle.max(inner_angle),
+            falloff,
+            intensity,
+            attenuation: Attenuation::None,
+        }
+    }
+
+    pub fn new_boxed(
+        position: Vec4,
+        direction: Vec4,
+        inner_angle: f64,
+        outer_angle: f64,
+        falloff: f64,
+        intensity: Color,
+    ) -> BoxLight {
+        Box::new(Self::new(
+            position,
+            direction,
+            inner_angle,
+            outer_angle,
+            falloff,
+            intensity,
+        ))
+    }
+}
+
+/// Rectangular light given by a corner and two edges.
+/// The rectangle is split into `usteps` x `vsteps` cells, each contributing
+/// one sample, which is what produces soft shadows.
+#[derive(Debug)]
+pub struct AreaLight {
+    pub corner: Vec4,
+    pub uvec: Vec4,
+    pub usteps: u32,
+    pub vvec: Vec4,
+    pub vsteps: u32,
+    /// Samples at random spots in their cells instead of the cell centers,
+    /// trading banding in the penumbra for noise
+    pub jitter: bool,
+    pub intensity: Color,
+    pub position: Vec4,
+    pub attenuation: Attenuation,
+}
+
+impl Light for AreaLight {
+    fn get_intensity(&self) -> Color {
+        self.intensity
+    }
+
+    fn samples(&self, p: &Vec4) -> Vec<LightSample> {
+        self.sample_points(p)
+            .iter()
+            .map(|point| LightSample::towards(p, point))
+            .collect()
+    }
+
+    fn attenuation(&self) -> Attenuation {
+        self.attenuation
+    }
+}
+
+impl AreaLight {
+    /// `full_uvec` and `full_vvec` are the whole edges of the light
+    pub fn new(
+        corner: Vec4,
+        full_uvec: Vec4,
+        usteps: u32,
+        full_vvec: Vec4,
+        vsteps: u32,
+        jitter: bool,
+        intensity: Color,
+    ) -> Self {
+        let usteps = usteps.max(1);
+        let vsteps = vsteps.max(1);
+        Self {
+            corner,
+            uvec: full_uvec / usteps as f64,
+            usteps,
+            vvec: full_vvec / vsteps as f64,
+            vsteps,
+            jitter,
+            intensity,
+            position: corner + full_uvec / 2.0 + full_vvec / 2.0,
+            attenuation: Attenuation::None,
+        }
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub fn new_boxed(
+        corner: Vec4,
+        full_uvec: Vec4,
+        usteps: u32,
+        full_vvec: Vec4,
+        vsteps: u32,
+        jitter: bool,
+        intensity: Color,
+    ) -> BoxLight {
+        Box::new(Self::new(
+            corner, full_uvec, usteps, full_vvec, vsteps, jitter, intensity,
+        ))
+    }
+
+    pub fn sample_count(&self) -> u32 {
+        self.usteps * self.vsteps
+    }
+
+    /// Point within the cell `u`, `v`, `offset` in `[0, 1)` picks it
+    pub fn point_on_light(&self, u: u32, v: u32, offset_u: f64, offset_v: f64) -> Vec4 {
+        self.corner + self.uvec * (u as f64 + offset_u) + self.vvec * (v as f64 + offset_v)
+    }
+
+    /// One point per cell as seen from `p`.
+    /// Jitter is seeded from `p`, so shading and shadows of a point
+    /// agree on the samples, independent of the rendering order.
+    pub fn sample_points(&self, p: &Vec4) -> Vec<Vec4> {
+        let mut rng = Rng::for_point(p);
+        let mut ret = Vec::with_capacity(self.sample_count() as usize);
+        for v in 0..self.vsteps {
+            for u in 0..self.usteps {
+                let point = if self.jitter {
+                    self.point_on_light(u, v, rng.next_f64(), rng.next_f64())
+                } else {
+                    self.point_on_light(u, v, 0.5, 0.5)
+                };
+                ret.push(point);
+            }
         }
+        ret
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::math::EPSILON;
+    use std::f64::consts::PI;
 
     #[test]
     fn basic() {
@@ -35,4 +363,169 @@ mod tests {
         assert_eq!(Vec4::POINT_ZERO, light.position);
         assert_eq!(Color::rgb(1.0, 1.0, 1.0), light.intensity);
     }
+
+    #[test]
+    fn area_light() {
+        let light = AreaLight::new(
+            Vec4::POINT_ZERO,
+            Vec4::vec(2.0, 0.0, 0.0),
+            4,
+            Vec4::vec(0.0, 0.0, 1.0),
+            2,
+            false,
+            Color::WHITE,
+        );
+        assert_eq!(light.corner, Vec4::POINT_ZERO);
+        assert_eq!(light.uvec, Vec4::vec(0.5, 0.0, 0.0));
+        assert_eq!(light.vvec, Vec4::vec(0.0, 0.0, 0.5));
+        assert_eq!(light.sample_count(), 8);
+        assert_eq!(light.position, Vec4::point(1.0, 0.0, 0.5));
+    }
+
+    #[test]
+    fn point_on_light() {
+        let light = AreaLight::new(
+            Vec4::POINT_ZERO,
+            Vec4::vec(2.0, 0.0, 0.0),
+            4,
+            Vec4::vec(0.0, 0.0, 1.0),
+            2,
+            false,
+            Color::WHITE,
+        );
+        let cells = [(0, 0), (1, 0), (0, 1), (2, 0), (3, 1)];
+        let exps = [
+            Vec4::point(0.25, 0.0, 0.25),
+            Vec4::point(0.75, 0.0, 0.25),
+            Vec4::point(0.25, 0.0, 0.75),
+            Vec4::point(1.25, 0.0, 0.25),
+            Vec4::point(1.75, 0.0, 0.75),
+        ];
+        for ((u, v), e) in cells.iter().zip(exps.iter()) {
+            assert_eq!(light.point_on_light(*u, *v, 0.5, 0.5), *e);
+        }
+        let samples = light.sample_points(&Vec4::POINT_ZERO);
+        assert_eq!(samples.len(), 8);
+        assert_eq!(samples[0], exps[0]);
+        assert_eq!(samples[7], exps[4]);
+    }
+
+    #[test]
+    fn jittered_samples() {
+        let mut light = AreaLight::new(
+            Vec4::POINT_ZERO,
+            Vec4::vec(2.0, 0.0, 0.0),
+            4,
+            Vec4::vec(0.0, 0.0, 1.0),
+            2,
+            true,
+            Color::WHITE,
+        );
+        let p = Vec4::point(1.0, 2.0, 3.0);
+        let samples = light.sample_points(&p);
+        assert_eq!(samples, light.sample_points(&p));
+        light.jitter = false;
+        let centers = light.sample_points(&p);
+        for (s, c) in samples.iter().zip(centers.iter()) {
+            // stays within its cell
+            assert!((s.x - c.x).abs() <= 0.25 && (s.z - c.z).abs() <= 0.25);
+            assert_eq!(s.y, 0.0);
+        }
+        assert_ne!(samples, centers);
+    }
+
+    #[test]
+    fn point_light_samples() {
+        let light = PointLight::new(Vec4::point(0.0, 3.0, 4.0), Color::WHITE);
+        let samples = light.samples(&Vec4::POINT_ZERO);
+        assert_eq!(samples.len(), 1);
+        assert_eq!(samples[0].direction, Vec4::vec(0.0, 0.6, 0.8));
+        assert_eq!(samples[0].distance, 5.0);
+        assert_eq!(light.intensity_at(&Vec4::POINT_ZERO), 1.0);
+    }
+
+    #[test]
+    fn directional_light() {
+        let light = DirectionalLight::new(Vec4::vec(0.0, -2.0, 0.0), Color::WHITE);
+        for p in [Vec4::POINT_ZERO, Vec4::point(100.0, -50.0, 3.0)] {
+            let samples = light.samples(&p);
+            assert_eq!(samples.len(), 1);
+            assert_eq!(samples[0].direction, Vec4::VEC_Y_ONE);
+            assert!(samples[0].distance.is_infinite());
+            assert_eq!(light.intensity_at(&p), 1.0);
+        }
+    }
+
+    #[test]
+    fn spot_light() {
+        let light = SpotLight::new(
+            Vec4::point(0.0, 10.0, 0.0),
+            -Vec4::VEC_Y_ONE,
+            PI / 8.0,
+            PI / 4.0,
+            1.0,
+            Color::WHITE,
+        );
+        let samples = light.samples(&Vec4::POINT_ZERO);
+        assert_eq!(samples[0].direction, Vec4::VEC_Y_ONE);
+        assert_eq!(samples[0].distance, 10.0);
+
+        // within inner cone, outside outer cone, behind the light
+        assert_eq!(light.intensity_at(&Vec4::point(1.0, 0.0, 0.0)), 1.0);
+        assert_eq!(light.intensity_at(&Vec4::point(20.0, 0.0, 0.0)), 0.0);
+        assert_eq!(light.intensity_at(&Vec4::point(0.0, 20.0, 0.0)), 0.0);
+
+        // halfway between the cones' cosines
+        let cos = ((PI / 8.0).cos() + (PI / 4.0).cos()) / 2.0;
+        let p = Vec4::point((1.0 - cos * cos).sqrt() * 10.0 / cos, 0.0, 0.0);
+        assert!((light.intensity_at(&p) - 0.5).abs() < EPSILON);
+
+        let sharp = SpotLight::new(
+            light.position,
+            light.direction,
+            light.inner_angle,
+            light.outer_angle,
+            2.0,
+            Color::WHITE,
+        );
+        assert!((sharp.intensity_at(&p) - 0.25).abs() < EPSILON);
+    }
+
+    #[test]
+    fn area_light_samples() {
+        let light = AreaLight::new(
+            Vec4::point(-1.0, 2.0, -1.0),
+            Vec4::vec(2.0, 0.0, 0.0),
+            2,
+            Vec4::vec(0.0, 0.0, 2.0),
+            2,
+            false,
+            Color::WHITE,
+        );
+        let samples = light.samples(&Vec4::POINT_ZERO);
+        assert_eq!(samples.len(), 4);
+        for sample in samples.iter() {
+            assert_eq!(sample.distance, 4.5f64.sqrt());
+            assert!(sample.direction.y > 0.0);
+        }
+    }
+
+    #[test]
+    fn attenuation() {
+        let distances = [0.0, 1.0, 2.0, 4.0];
+        let none = [1.0, 1.0, 1.0, 1.0];
+        let linear = [1.0, 0.5, 1.0 / 3.0, 0.2];
+        let quadratic = [1.0, 0.5, 0.2, 1.0 / 17.0];
+        let inverse_square = [1.0, 1.0, 0.25, 0.0625];
+        for (i, d) in distances.iter().enumerate() {
+            assert_eq!(Attenuation::None.factor(*d), none[i]);
+            assert!((Attenuation::Linear(1.0).factor(*d) - linear[i]).abs() < EPSILON);
+            assert!((Attenuation::Quadratic(1.0).factor(*d) - quadratic[i]).abs() < EPSILON);
+            let f = Attenuation::InverseSquare { radius: 1.0 }.factor(*d);
+            assert!((f - inverse_square[i]).abs() < EPSILON, "Failed for {}", d);
+        }
+        assert_eq!(PointLight::default().attenuation(), Attenuation::None);
+        let sun = DirectionalLight::new(-Vec4::VEC_Y_ONE, Color::WHITE);
+        assert_eq!(sun.attenuation(), Attenuation::None);
+    }
 }
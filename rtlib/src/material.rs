@@ -1,9 +1,29 @@
 use crate::color::Color;
-use crate::light::PointLight;
+use crate::light::Light;
 use crate::math::vec4::Vec4;
 use crate::patterns::BoxPattern;
 use crate::shapes::Shape;
 
+use std::f64::consts::PI;
+
+/// How a material reflects light
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum ShadingModel {
+    /// Classic Phong, using the material's `diffuse`, `specular`
+    /// and `shininess`
+    #[default]
+    Phong,
+    /// Physically based Cook-Torrance model with GGX distribution,
+    /// Smith geometry and Schlick Fresnel. Energy conserving, so
+    /// `diffuse`, `specular` and `shininess` are ignored.
+    Microfacet {
+        /// `0.0` is a perfect mirror, `1.0` fully rough
+        roughness: f64,
+        /// `0.0` for dielectrics like plastic, `1.0` for metals
+        metalness: f64,
+    },
+}
+
 #[derive(Debug, PartialEq, Clone)]
 pub struct Material {
     pub color: Color,
@@ -15,6 +35,16 @@ pub struct Material {
     pub reflectivness: f64,
     pub transparency: f64,
     pub refractive_index: f64,
+    /// Objects that don't cast shadows are ignored by shadow rays
+    pub casts_shadow: bool,
+    pub shading: ShadingModel,
+    /// Light given off by the surface itself, added regardless of any lights
+    pub emissive: Color,
+    /// Color light inside a transparent object fades to (Beer-Lambert),
+    /// reached after one unit of distance at `absorption_density` of `1.0`
+    pub absorption: Color,
+    /// `0.0` disables absorption, higher values deepen the color faster
+    pub absorption_density: f64,
 }
 
 impl Material {
@@ -28,6 +58,11 @@ impl Material {
         reflectivness: 0.9,
         transparency: 1.0,
         refractive_index: 1.5,
+        casts_shadow: true,
+        shading: ShadingModel::Phong,
+        emissive: Color::BLACK,
+        absorption: Color::WHITE,
+        absorption_density: 0.0,
     };
 
     pub fn new(
@@ -51,60 +86,169 @@ impl Material {
             reflectivness,
             transparency,
             refractive_index,
+            casts_shadow: true,
+            shading: ShadingModel::Phong,
+            emissive: Color::BLACK,
+            absorption: Color::WHITE,
+            absorption_density: 0.0,
         }
     }
 
+    /// Color of the surface at `p`, from the pattern if there's one
+    pub fn color_at(&self, object: &dyn Shape, p: &Vec4) -> Color {
+        match &self.pattern {
+            Some(pat) => pat.pattern_at(object, *p),
+            None => self.color,
+        }
+    }
+
+    /// Light passing through the surface, tinted by its color.
+    /// Black for opaque surfaces.
+    pub fn transmission_at(&self, object: &dyn Shape, p: &Vec4) -> Color {
+        if self.transparency <= 0.0 {
+            return Color::BLACK;
+        }
+        self.color_at(object, p) * self.transparency
+    }
+
+    /// Part of the light left after travelling `distance` inside the object
+    pub fn absorption_over(&self, distance: f64) -> Color {
+        if self.absorption_density <= 0.0 {
+            return Color::WHITE;
+        }
+        let exponent = self.absorption_density * distance;
+        Color::rgb(
+            self.absorption.r.powf(exponent),
+            self.absorption.g.powf(exponent),
+            self.absorption.b.powf(exponent),
+        )
+    }
+
+    /// Phong shading of `p`, averaged over the samples of the light.
+    /// `light_intensity` is the part of the light reaching `p` per channel,
+    /// black in full shadow, white when fully lit and tinted when
+    /// the light passes through colored glass.
     pub fn lighting(
         &self,
         object: &dyn Shape,
         p: &Vec4,
-        light: &PointLight,
+        light: &dyn Light,
         eye_vec: &Vec4,
         normal: &Vec4,
-        in_snadow: bool,
+        light_intensity: Color,
     ) -> Color {
-        let color = match &self.pattern {
-            Some(pat) => pat.pattern_at(object, *p),
-            None => self.color,
-        };
+        let color = self.color_at(object, p);
+
+        // Ambient contribution
+        let ambient = color * light.get_intensity() * self.ambient;
+        ambient + self.direct(&color, p, light, eye_vec, normal, light_intensity)
+    }
+
+    /// `lighting` without the ambient term, the light reaching the eye
+    /// straight from the light source
+    pub fn direct_lighting(
+        &self,
+        object: &dyn Shape,
+        p: &Vec4,
+        light: &dyn Light,
+        eye_vec: &Vec4,
+        normal: &Vec4,
+        light_intensity: Color,
+    ) -> Color {
+        let color = self.color_at(object, p);
+        self.direct(&color, p, light, eye_vec, normal, light_intensity)
+    }
+
+    /// Diffuse and specular of the surface `color`
+    fn direct(
+        &self,
+        color: &Color,
+        p: &Vec4,
+        light: &dyn Light,
+        eye_vec: &Vec4,
+        normal: &Vec4,
+        light_intensity: Color,
+    ) -> Color {
+        // if in shadow, then ignore diffuse and specular
+        if light_intensity == Color::BLACK {
+            return Color::BLACK;
+        }
+        let samples = light.samples(p);
+        if samples.is_empty() {
+            return Color::BLACK;
+        }
 
         // combine the surface color with the light's color intensity
-        let eff_color = color * light.intensity;
+        let light_color = light.get_intensity();
+        let eff_color = *color * light_color;
 
-        // Direction to the light source
-        let light_dir = (light.position - p).normalize();
+        let attenuation = light.attenuation();
+        let mut diffuse = Color::BLACK;
+        let mut specular = Color::BLACK;
+        for sample in samples.iter() {
+            // Direction to the light source
+            let light_dir = sample.direction;
+            let falloff = attenuation.factor(sample.distance);
 
-        // Ambient contribution
-        let ambient = eff_color * self.ambient;
+            // check if the light is behind the surface
+            let light_dot_normal = light_dir.dot(normal);
+            if light_dot_normal < 0.0 {
+                // the light is behind the surface, so diffuse and specular are black
+                continue;
+            }
 
-        // if in shadow, then ignore diffuse and specular
-        if in_snadow {
-            return ambient;
+            let (dif, spec) = match self.shading {
+                ShadingModel::Phong => self.phong(
+                    &eff_color,
+                    &light_color,
+                    &light_dir,
+                    light_dot_normal,
+                    eye_vec,
+                    normal,
+                ),
+                ShadingModel::Microfacet {
+                    roughness,
+                    metalness,
+                } => microfacet(
+                    color,
+                    &light_color,
+                    &light_dir,
+                    eye_vec,
+                    normal,
+                    roughness,
+                    metalness,
+                ),
+            };
+            diffuse = diffuse + dif * falloff;
+            specular = specular + spec * falloff;
         }
+        let scale = light_intensity * (light.intensity_at(p) / samples.len() as f64);
+        diffuse * scale + specular * scale
+    }
 
-        // check if the light is behind the surface
-        let light_dot_normal = light_dir.dot(normal);
+    /// Diffuse and specular of a single light direction
+    fn phong(
+        &self,
+        eff_color: &Color,
+        light_color: &Color,
+        light_dir: &Vec4,
+        light_dot_normal: f64,
+        eye_vec: &Vec4,
+        normal: &Vec4,
+    ) -> (Color, Color) {
+        // get the diffuse
+        let diffuse = *eff_color * self.diffuse * light_dot_normal;
 
-        let (diffuse, specular) = if light_dot_normal < 0.0 {
-            // the light is behind the surface, so diffuse and specular are black
-            (Color::BLACK, Color::BLACK)
+        // check reflection direction relative to the eye
+        let reflect_dir = (-*light_dir).reflect(normal);
+        let reflect_dot_eye = reflect_dir.dot(eye_vec);
+        let specular = if reflect_dot_eye < 0.0 {
+            Color::BLACK
         } else {
-            // get the diffuse
-            let dif = eff_color * self.diffuse * light_dot_normal;
-
-            // check reflection direction relative to the eye
-            let reflect_dir = (-light_dir).reflect(normal);
-            let reflect_dot_eye = reflect_dir.dot(eye_vec);
-
-            let spec = if reflect_dot_eye < 0.0 {
-                Color::BLACK
-            } else {
-                let factor = reflect_dot_eye.powf(self.shininess);
-                light.intensity * self.specular * factor
-            };
-            (dif, spec)
+            let factor = reflect_dot_eye.powf(self.shininess);
+            *light_color * self.specular * factor
         };
-        ambient + diffuse + specular
+        (diffuse, specular)
     }
 
     pub fn is_reflective_and_transparent(&self) -> bool {
@@ -112,6 +256,62 @@ impl Material {
     }
 }
 
+/// Diffuse and specular of a single light direction with the Cook-Torrance model.
+/// Light intensity is taken as the irradiance on a surface facing it,
+/// hence the `PI` factor, so a white Lambertian surface facing a white light
+/// comes out white just like with Phong.
+fn microfacet(
+    color: &Color,
+    light_color: &Color,
+    light_dir: &Vec4,
+    eye_vec: &Vec4,
+    normal: &Vec4,
+    roughness: f64,
+    metalness: f64,
+) -> (Color, Color) {
+    let n_dot_l = light_dir.dot(normal).max(0.0);
+    // grazing views would blow up the specular term
+    let n_dot_v = eye_vec.dot(normal).max(1e-4);
+    let halfway = (*light_dir + *eye_vec).normalize();
+    let n_dot_h = normal.dot(&halfway).max(0.0);
+    let v_dot_h = eye_vec.dot(&halfway).max(0.0);
+
+    // dielectrics reflect about 4% head on, metals tint the reflection
+    let dielectric = Color::rgb(0.04, 0.04, 0.04);
+    let f0 = dielectric * (1.0 - metalness) + *color * metalness;
+    let fresnel = schlick_fresnel(&f0, v_dot_h);
+
+    let d = ggx_distribution(n_dot_h, roughness);
+    let g = smith_geometry(n_dot_l, n_dot_v, roughness);
+    let specular = fresnel * (d * g * PI / (4.0 * n_dot_v)) * *light_color;
+
+    // whatever isn't reflected is refracted and diffused, except in metals
+    let k_diffuse = (Color::WHITE - fresnel) * (1.0 - metalness);
+    let diffuse = k_diffuse * *color * *light_color * n_dot_l;
+    (diffuse, specular)
+}
+
+/// GGX (Trowbridge-Reitz) normal distribution
+fn ggx_distribution(n_dot_h: f64, roughness: f64) -> f64 {
+    // perfectly smooth surfaces make the distribution a spike
+    let alpha = (roughness * roughness).max(1e-4);
+    let alpha2 = alpha * alpha;
+    let denom = n_dot_h * n_dot_h * (alpha2 - 1.0) + 1.0;
+    alpha2 / (PI * denom * denom)
+}
+
+/// Smith's shadowing-masking with the Schlick-GGX approximation
+fn smith_geometry(n_dot_l: f64, n_dot_v: f64, roughness: f64) -> f64 {
+    let k = (roughness + 1.0) * (roughness + 1.0) / 8.0;
+    let g1 = |n_dot_x: f64| n_dot_x / (n_dot_x * (1.0 - k) + k);
+    g1(n_dot_l) * g1(n_dot_v)
+}
+
+/// Schlick's approximation of the Fresnel reflectance
+fn schlick_fresnel(f0: &Color, cos: f64) -> Color {
+    *f0 + (Color::WHITE - *f0) * (1.0 - cos).powi(5)
+}
+
 impl Default for Material {
     fn default() -> Self {
         Self {
@@ -124,6 +324,11 @@ impl Default for Material {
             reflectivness: 0.0,
             transparency: 0.0,
             refractive_index: 1.0,
+            casts_shadow: true,
+            shading: ShadingModel::Phong,
+            emissive: Color::BLACK,
+            absorption: Color::WHITE,
+            absorption_density: 0.0,
         }
     }
 }
@@ -131,6 +336,8 @@ impl Default for Material {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::light::{AreaLight, Attenuation, PointLight, SpotLight};
+    use crate::math::{EPSILON, SQRT_2};
     use crate::patterns;
     use crate::shapes::Sphere;
 
@@ -144,6 +351,47 @@ mod tests {
         assert_eq!(0.9, m.specular);
         assert_eq!(200.0, m.shininess);
         assert_eq!(0.0, m.reflectivness);
+        assert!(m.casts_shadow);
+        assert_eq!(Color::BLACK, m.emissive);
+    }
+
+    #[test]
+    fn absorption() {
+        let mut m = Material::GLASS;
+        assert_eq!(m.absorption_over(10.0), Color::WHITE);
+        m.absorption = Color::rgb(0.5, 1.0, 0.8);
+        assert_eq!(m.absorption_over(10.0), Color::WHITE);
+        m.absorption_density = 1.0;
+        assert_eq!(m.absorption_over(0.0), Color::WHITE);
+        assert_eq!(m.absorption_over(1.0), Color::rgb(0.5, 1.0, 0.8));
+        assert_eq!(m.absorption_over(2.0), Color::rgb(0.25, 1.0, 0.64));
+        m.absorption_density = 0.5;
+        assert_eq!(m.absorption_over(2.0), Color::rgb(0.5, 1.0, 0.8));
+    }
+
+    #[test]
+    fn transmission() {
+        let s = Sphere::default();
+        let p = Vec4::POINT_ZERO;
+        let m = Material::default();
+        assert_eq!(m.transmission_at(&s, &p), Color::BLACK);
+        let mut m = Material::GLASS;
+        assert_eq!(m.transmission_at(&s, &p), Color::WHITE);
+        m.color = Color::rgb(1.0, 0.5, 0.0);
+        m.transparency = 0.5;
+        assert_eq!(m.transmission_at(&s, &p), Color::rgb(0.5, 0.25, 0.0));
+    }
+
+    #[test]
+    fn tinted_lighting() {
+        let m = Material::default();
+        let s = Sphere::default();
+        let p = Vec4::POINT_ZERO;
+        let eye_vec = Vec4::vec(0.0, 0.0, -1.0);
+        let normal = Vec4::vec(0.0, 0.0, -1.0);
+        let light = PointLight::new(Vec4::point(0.0, 0.0, -10.0), Color::WHITE);
+        let c = m.lighting(&s, &p, &light, &eye_vec, &normal, Color::rgb(1.0, 0.5, 0.0));
+        assert_eq!(c, Color::rgb(1.9, 1.0, 0.1));
     }
 
     #[test]
@@ -158,7 +406,7 @@ mod tests {
         let light = PointLight::new(Vec4::point(0.0, 0.0, -10.0), Color::rgb(1.0, 1.0, 1.0));
         assert_eq!(
             Color::rgb(1.9, 1.9, 1.9),
-            m.lighting(&s, &p, &light, &eye_vec, &normal, false)
+            m.lighting(&s, &p, &light, &eye_vec, &normal, Color::WHITE)
         );
 
         // Eye PI/2 off normal
@@ -167,7 +415,7 @@ mod tests {
         let light = PointLight::new(Vec4::point(0.0, 0.0, -10.0), Color::rgb(1.0, 1.0, 1.0));
         assert_eq!(
             Color::rgb(1.0, 1.0, 1.0),
-            m.lighting(&s, &p, &light, &eye_vec, &normal, false)
+            m.lighting(&s, &p, &light, &eye_vec, &normal, Color::WHITE)
         );
 
         // Eye on normal, light PI/2 offsets
@@ -177,7 +425,7 @@ mod tests {
         let c = 0.1 + 0.9 * 2f64.sqrt() / 2.0 + 0.0; // 0.7364
         assert_eq!(
             Color::rgb(c, c, c),
-            m.lighting(&s, &p, &light, &eye_vec, &normal, false)
+            m.lighting(&s, &p, &light, &eye_vec, &normal, Color::WHITE)
         );
 
         // Light PI/2 offsets, eye directly on reflection path
@@ -187,7 +435,7 @@ mod tests {
         let c = 1.6363961030678928; // 0.1 + 0.9 * 2f64.sqrt() / 2.0 + 0.9; // 1.6364
         assert_eq!(
             Color::rgb(c, c, c),
-            m.lighting(&s, &p, &light, &eye_vec, &normal, false)
+            m.lighting(&s, &p, &light, &eye_vec, &normal, Color::WHITE)
         );
 
         // Light behind the surface
@@ -196,7 +444,7 @@ mod tests {
         let light = PointLight::new(Vec4::point(0.0, 0.0, 10.0), Color::rgb(1.0, 1.0, 1.0));
         assert_eq!(
             Color::rgb(0.1, 0.1, 0.1),
-            m.lighting(&s, &p, &light, &eye_vec, &normal, false)
+            m.lighting(&s, &p, &light, &eye_vec, &normal, Color::WHITE)
         );
 
         // Lighting with the surface in shadow
@@ -205,7 +453,7 @@ mod tests {
         let light = PointLight::new(Vec4::point(0.0, 0.0, -10.0), Color::rgb(1.0, 1.0, 1.0));
         assert_eq!(
             Color::rgb(0.1, 0.1, 0.1),
-            m.lighting(&s, &p, &light, &eye_vec, &normal, true)
+            m.lighting(&s, &p, &light, &eye_vec, &normal, Color::BLACK)
         );
     }
 
@@ -226,7 +474,7 @@ mod tests {
             &light,
             &eye_vec,
             &normal,
-            false,
+            Color::WHITE,
         );
         let c2 = m.lighting(
             &object,
@@ -234,10 +482,196 @@ mod tests {
             &light,
             &eye_vec,
             &normal,
-            false,
+            Color::WHITE,
         );
 
         assert_eq!(c1, Color::WHITE);
         assert_eq!(c2, Color::BLACK);
     }
+
+    #[test]
+    fn lighting_intensity() {
+        let m = Material::default();
+        let s = Sphere::default();
+        let p = Vec4::point(0.0, 0.0, -1.0);
+        let eye_vec = Vec4::vec(0.0, 0.0, -1.0);
+        let normal = Vec4::vec(0.0, 0.0, -1.0);
+        let light = PointLight::new(Vec4::point(0.0, 0.0, -10.0), Color::WHITE);
+        let intensities = [1.0, 0.5, 0.0];
+        let exps = [1.9, 1.0, 0.1];
+        for (i, e) in intensities.iter().zip(exps.iter()) {
+            assert_eq!(
+                m.lighting(&s, &p, &light, &eye_vec, &normal, Color::rgb(*i, *i, *i)),
+                Color::rgb(*e, *e, *e)
+            );
+        }
+    }
+
+    #[test]
+    fn area_lighting() {
+        let light = AreaLight::new(
+            Vec4::point(-0.5, -0.5, -5.0),
+            Vec4::vec(1.0, 0.0, 0.0),
+            2,
+            Vec4::vec(0.0, 1.0, 0.0),
+            2,
+            false,
+            Color::WHITE,
+        );
+        let mut m = Material::default();
+        m.ambient = 0.1;
+        m.diffuse = 0.9;
+        m.specular = 0.0;
+        m.color = Color::WHITE;
+        let s = Sphere::default();
+        let eye = Vec4::point(0.0, 0.0, -5.0);
+        let points = [
+            Vec4::point(0.0, 0.0, -1.0),
+            Vec4::point(0.0, 0.7071, -0.7071),
+        ];
+        let exps = [
+            Color::rgb(0.9965, 0.9965, 0.9965),
+            Color::rgb(0.62318, 0.62318, 0.62318),
+        ];
+        for (p, e) in points.iter().zip(exps.iter()) {
+            let eye_vec = (eye - p).normalize();
+            let normal = Vec4::vec(p.x, p.y, p.z);
+            let c = m.lighting(&s, p, &light, &eye_vec, &normal, Color::WHITE);
+            assert!((c.r - e.r).abs() < 1e-4, "{:?} {:?}", c, e);
+        }
+    }
+
+    #[test]
+    fn spot_lighting() {
+        let m = Material::default();
+        let s = Sphere::default();
+        let eye_vec = Vec4::vec(0.0, 0.0, -1.0);
+        let normal = Vec4::vec(0.0, 0.0, -1.0);
+        let light = SpotLight::new(
+            Vec4::point(0.0, 0.0, -10.0),
+            Vec4::VEC_Z_ONE,
+            0.1,
+            0.2,
+            1.0,
+            Color::WHITE,
+        );
+        let lit = m.lighting(
+            &s,
+            &Vec4::POINT_ZERO,
+            &light,
+            &eye_vec,
+            &normal,
+            Color::WHITE,
+        );
+        assert_eq!(lit, Color::rgb(1.9, 1.9, 1.9));
+        // outside the cone only the ambient is left
+        let p = Vec4::point(5.0, 0.0, 0.0);
+        let unlit = m.lighting(&s, &p, &light, &eye_vec, &normal, Color::WHITE);
+        assert_eq!(unlit, Color::rgb(0.1, 0.1, 0.1));
+    }
+
+    #[test]
+    fn attenuated_lighting() {
+        let m = Material::default();
+        let s = Sphere::default();
+        let eye_vec = Vec4::vec(0.0, 0.0, -1.0);
+        let normal = Vec4::vec(0.0, 0.0, -1.0);
+        let mut light = PointLight::new(Vec4::point(0.0, 0.0, -10.0), Color::WHITE);
+        let near = Vec4::point(0.0, 0.0, -8.0);
+        let far = Vec4::POINT_ZERO;
+        assert_eq!(
+            m.lighting(&s, &near, &light, &eye_vec, &normal, Color::WHITE),
+            m.lighting(&s, &far, &light, &eye_vec, &normal, Color::WHITE)
+        );
+
+        light.attenuation = Attenuation::InverseSquare { radius: 1.0 };
+        // 2 and 10 units away, only diffuse and specular fade
+        let c = m.lighting(&s, &near, &light, &eye_vec, &normal, Color::WHITE);
+        assert_eq!(
+            c,
+            Color::rgb(0.1 + 1.8 / 4.0, 0.1 + 1.8 / 4.0, 0.1 + 1.8 / 4.0)
+        );
+        let c = m.lighting(&s, &far, &light, &eye_vec, &normal, Color::WHITE);
+        assert_eq!(c, Color::rgb(0.118, 0.118, 0.118));
+    }
+
+    #[test]
+    fn microfacet_terms() {
+        // rough surface seen head on
+        assert!((ggx_distribution(1.0, 1.0) - 1.0 / PI).abs() < EPSILON);
+        assert!((smith_geometry(1.0, 1.0, 1.0) - 1.0).abs() < EPSILON);
+        let f0 = Color::rgb(0.04, 0.04, 0.04);
+        assert_eq!(schlick_fresnel(&f0, 1.0), f0);
+        assert_eq!(schlick_fresnel(&f0, 0.0), Color::WHITE);
+        // smoother surfaces concentrate the highlight
+        assert!(ggx_distribution(1.0, 0.2) > ggx_distribution(1.0, 0.5));
+        assert!(ggx_distribution(0.9, 0.2) < ggx_distribution(0.9, 0.5));
+    }
+
+    fn microfacet_material(color: Color, roughness: f64, metalness: f64) -> Material {
+        Material {
+            color,
+            ambient: 0.0,
+            shading: ShadingModel::Microfacet {
+                roughness,
+                metalness,
+            },
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn microfacet_lighting() {
+        let s = Sphere::default();
+        let p = Vec4::POINT_ZERO;
+        let normal = Vec4::vec(0.0, 0.0, -1.0);
+        let light = PointLight::new(Vec4::point(0.0, 10.0, -10.0), Color::WHITE);
+        let eye_on_normal = Vec4::vec(0.0, 0.0, -1.0);
+        let eye_on_reflection = Vec4::vec(0.0, -SQRT_2 / 2.0, -SQRT_2 / 2.0);
+
+        // rough plastic is mostly diffuse, close to Lambert
+        let m = microfacet_material(Color::WHITE, 1.0, 0.0);
+        let c = m.lighting(&s, &p, &light, &eye_on_normal, &normal, Color::WHITE);
+        assert!(c.r > 0.6 && c.r < SQRT_2 / 2.0, "{:?}", c);
+
+        // a smooth metal only shows the light in the mirror direction
+        let gold = Color::rgb(1.0, 0.8, 0.3);
+        let m = microfacet_material(gold, 0.1, 1.0);
+        let off = m.lighting(&s, &p, &light, &eye_on_normal, &normal, Color::WHITE);
+        let on = m.lighting(&s, &p, &light, &eye_on_reflection, &normal, Color::WHITE);
+        assert!(off.r < 0.01, "{:?}", off);
+        assert!(on.r > 1.0, "{:?}", on);
+        // tinted by the metal
+        assert!(on.r > on.g && on.g > on.b);
+
+        // energy conservation, the light reflected over the hemisphere
+        // of views never exceeds the irradiance `n_dot_l`
+        for (roughness, metalness) in [(0.05, 0.0), (0.5, 0.0), (1.0, 0.0), (0.5, 1.0)] {
+            let m = microfacet_material(Color::WHITE, roughness, metalness);
+            let (theta_steps, phi_steps) = (64, 128);
+            let d_theta = PI / 2.0 / theta_steps as f64;
+            let d_phi = 2.0 * PI / phi_steps as f64;
+            let mut reflected = 0.0;
+            for i in 0..theta_steps {
+                let theta = (i as f64 + 0.5) * d_theta;
+                for j in 0..phi_steps {
+                    let phi = (j as f64 + 0.5) * d_phi;
+                    let eye = Vec4::vec(
+                        theta.sin() * phi.cos(),
+                        theta.sin() * phi.sin(),
+                        -theta.cos(),
+                    );
+                    let c = m.lighting(&s, &p, &light, &eye, &normal, Color::WHITE);
+                    reflected += c.r * theta.cos() * theta.sin() * d_theta * d_phi;
+                }
+            }
+            assert!(
+                reflected / PI <= SQRT_2 / 2.0 * 1.01,
+                "{} {} reflects {}",
+                roughness,
+                metalness,
+                reflected / PI
+            );
+        }
+    }
 }
@@ -1,4 +1,5 @@
 pub mod matrix;
+pub mod quaternion;
 pub mod vec4;
 
 /// Square root of 2 as const
@@ -1,3 +1,4 @@
+use crate::math::quaternion::Quaternion;
 use crate::math::vec4::Vec4;
 use std::ops::Mul;
 
@@ -152,6 +153,84 @@ impl Mat4 {
         };
         orientation * Self::translation(-from.x, -from.y, -from.z)
     }
+
+    /// Blends the matrices entry by entry, `t` of `0.0` gives `self`
+    /// and `1.0` gives `other`.
+    /// Rotations don't blend like this, see `Decomposition` for that.
+    pub fn lerp(&self, other: &Self, t: f64) -> Self {
+        let mut ret = Self::ZERO;
+        for r in 0..4 {
+            for c in 0..4 {
+                ret.data[r][c] = self.data[r][c] + (other.data[r][c] - self.data[r][c]) * t;
+            }
+        }
+        ret
+    }
+
+    /// Splits the transform into a translation, a rotation and a stretch
+    /// (polar decomposition). Fails if the matrix isn't invertible.
+    pub fn decompose(&self) -> Result<Decomposition, &'static str> {
+        let translation = Vec4::vec(self.data[0][3], self.data[1][3], self.data[2][3]);
+        let mut linear = *self;
+        linear.data[0][3] = 0.0;
+        linear.data[1][3] = 0.0;
+        linear.data[2][3] = 0.0;
+        linear.data[3] = [0.0, 0.0, 0.0, 1.0];
+
+        // Averaging a matrix with its inverse transpose converges
+        // to the closest rotation (or reflection)
+        let mut rotation = linear;
+        for _ in 0..100 {
+            let next = rotation.lerp(&rotation.inverse()?.transpose(), 0.5);
+            let done = (0..3)
+                .all(|r| (0..3).all(|c| (next.data[r][c] - rotation.data[r][c]).abs() < 1e-15));
+            rotation = next;
+            if done {
+                break;
+            }
+        }
+        // a mirrored transform is a rotation with a negative stretch
+        if rotation.determinant() < 0.0 {
+            for r in 0..3 {
+                for c in 0..3 {
+                    rotation.data[r][c] = -rotation.data[r][c];
+                }
+            }
+        }
+        Ok(Decomposition {
+            translation,
+            rotation: Quaternion::from_rotation(&rotation),
+            stretch: rotation.transpose() * linear,
+        })
+    }
+}
+
+/// Transform split up so that its parts blend independently.
+/// Put back together as `translation * rotation * stretch`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Decomposition {
+    pub translation: Vec4,
+    pub rotation: Quaternion,
+    /// Scaling along some axes, a symmetric matrix
+    pub stretch: Mat4,
+}
+
+impl Decomposition {
+    /// Blend of the two, `t` of `0.0` gives `self` and `1.0` gives `other`.
+    /// Rotation goes around the shorter way at constant speed, the rest
+    /// changes linearly.
+    pub fn interpolate(&self, other: &Self, t: f64) -> Self {
+        Self {
+            translation: self.translation + (other.translation - self.translation) * t,
+            rotation: self.rotation.slerp(&other.rotation, t),
+            stretch: self.stretch.lerp(&other.stretch, t),
+        }
+    }
+
+    pub fn to_matrix(&self) -> Mat4 {
+        let t = &self.translation;
+        Mat4::translation(t.x, t.y, t.z) * self.rotation.to_matrix() * self.stretch
+    }
 }
 
 impl Default for Mat4 {
@@ -889,4 +968,57 @@ mod tests {
         };
         approx_eq(&t, &exp);
     }
+
+    #[test]
+    fn decompose() {
+        let transforms = [
+            Mat4::IDENTITY,
+            Mat4::translation(1.0, -2.0, 3.0),
+            Mat4::rotation_y(PI),
+            Mat4::scaling(-1.0, 2.0, 0.5),
+            Mat4::translation(1.0, 0.0, 2.0) * Mat4::rotation_z(1.0) * Mat4::scaling(2.0, 1.0, 3.0),
+            Mat4::scaling(2.0, 1.0, 1.0) * Mat4::rotation_x(0.5),
+            Mat4::shearing(1.0, 0.0, 0.5, 0.0, 0.0, 2.0) * Mat4::rotation_y(2.0),
+        ];
+        for m in transforms.iter() {
+            let d = m.decompose().unwrap();
+            approx_eq(&d.to_matrix(), m);
+            approx_eq(&d.stretch, &d.stretch.transpose());
+        }
+        assert!(Mat4::scaling(1.0, 0.0, 1.0).decompose().is_err());
+    }
+
+    #[test]
+    fn interpolate() {
+        let a = Mat4::translation(0.0, 2.0, -4.0).decompose().unwrap();
+        let b = (Mat4::translation(4.0, 2.0, 0.0) * Mat4::scaling(3.0, 1.0, 1.0))
+            .decompose()
+            .unwrap();
+        approx_eq(
+            &a.interpolate(&b, 0.25).to_matrix(),
+            &(Mat4::translation(1.0, 2.0, -3.0) * Mat4::scaling(1.5, 1.0, 1.0)),
+        );
+
+        // turning half way keeps the size
+        let a = Mat4::scaling(2.0, 1.0, 1.0).decompose().unwrap();
+        let b = (Mat4::rotation_y(PI) * Mat4::scaling(2.0, 1.0, 1.0))
+            .decompose()
+            .unwrap();
+        approx_eq(
+            &a.interpolate(&b, 0.5).to_matrix(),
+            &(Mat4::rotation_y(PI / 2.0) * Mat4::scaling(2.0, 1.0, 1.0)),
+        );
+    }
+
+    #[test]
+    fn lerp() {
+        let a = Mat4::translation(0.0, 2.0, -4.0);
+        let b = Mat4::translation(4.0, 2.0, 0.0) * Mat4::scaling(3.0, 1.0, 1.0);
+        assert_eq!(a.lerp(&b, 0.0), a);
+        assert_eq!(a.lerp(&b, 1.0), b);
+        assert_eq!(
+            a.lerp(&b, 0.25),
+            Mat4::translation(1.0, 2.0, -3.0) * Mat4::scaling(1.5, 1.0, 1.0)
+        );
+    }
 }
@@ -0,0 +1,172 @@
+use crate::math::matrix::Mat4;
+
+/// Unit quaternion describing a rotation
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Quaternion {
+    pub w: f64,
+    pub x: f64,
+    pub y: f64,
+    pub z: f64,
+}
+
+impl Quaternion {
+    pub const IDENTITY: Self = Self {
+        w: 1.0,
+        x: 0.0,
+        y: 0.0,
+        z: 0.0,
+    };
+
+    pub fn new(w: f64, x: f64, y: f64, z: f64) -> Self {
+        Self { w, x, y, z }
+    }
+
+    /// Rotation part of `m`, its upper 3x3 has to be a rotation matrix
+    pub fn from_rotation(m: &Mat4) -> Self {
+        let d = &m.data;
+        let trace = d[0][0] + d[1][1] + d[2][2];
+        // divide by the largest component to stay precise
+        let ret = if trace > 0.0 {
+            let s = (trace + 1.0).sqrt() * 2.0;
+            Self::new(
+                s / 4.0,
+                (d[2][1] - d[1][2]) / s,
+                (d[0][2] - d[2][0]) / s,
+                (d[1][0] - d[0][1]) / s,
+            )
+        } else if d[0][0] > d[1][1] && d[0][0] > d[2][2] {
+            let s = (1.0 + d[0][0] - d[1][1] - d[2][2]).sqrt() * 2.0;
+            Self::new(
+                (d[2][1] - d[1][2]) / s,
+                s / 4.0,
+                (d[0][1] + d[1][0]) / s,
+                (d[0][2] + d[2][0]) / s,
+            )
+        } else if d[1][1] > d[2][2] {
+            let s = (1.0 + d[1][1] - d[0][0] - d[2][2]).sqrt() * 2.0;
+            Self::new(
+                (d[0][2] - d[2][0]) / s,
+                (d[0][1] + d[1][0]) / s,
+                s / 4.0,
+                (d[1][2] + d[2][1]) / s,
+            )
+        } else {
+            let s = (1.0 + d[2][2] - d[0][0] - d[1][1]).sqrt() * 2.0;
+            Self::new(
+                (d[1][0] - d[0][1]) / s,
+                (d[0][2] + d[2][0]) / s,
+                (d[1][2] + d[2][1]) / s,
+                s / 4.0,
+            )
+        };
+        ret.normalize()
+    }
+
+    pub fn to_matrix(&self) -> Mat4 {
+        let Self { w, x, y, z } = *self;
+        Mat4::from([
+            [
+                1.0 - 2.0 * (y * y + z * z),
+                2.0 * (x * y - w * z),
+                2.0 * (x * z + w * y),
+                0.0,
+            ],
+            [
+                2.0 * (x * y + w * z),
+                1.0 - 2.0 * (x * x + z * z),
+                2.0 * (y * z - w * x),
+                0.0,
+            ],
+            [
+                2.0 * (x * z - w * y),
+                2.0 * (y * z + w * x),
+                1.0 - 2.0 * (x * x + y * y),
+                0.0,
+            ],
+            [0.0, 0.0, 0.0, 1.0],
+        ])
+    }
+
+    pub fn dot(&self, other: &Self) -> f64 {
+        self.w * other.w + self.x * other.x + self.y * other.y + self.z * other.z
+    }
+
+    pub fn normalize(&self) -> Self {
+        let len = self.dot(self).sqrt();
+        Self::new(self.w / len, self.x / len, self.y / len, self.z / len)
+    }
+
+    /// Rotation `t` of the way from `self` to `other` at constant speed,
+    /// along the shorter way around
+    pub fn slerp(&self, other: &Self, t: f64) -> Self {
+        let mut other = *other;
+        let mut cos = self.dot(&other);
+        if cos < 0.0 {
+            other = Self::new(-other.w, -other.x, -other.y, -other.z);
+            cos = -cos;
+        }
+        let (a, b) = if cos > 0.9995 {
+            // nearly the same rotation, a straight blend is as good
+            (1.0 - t, t)
+        } else {
+            let angle = cos.acos();
+            let sin = angle.sin();
+            (((1.0 - t) * angle).sin() / sin, (t * angle).sin() / sin)
+        };
+        Self::new(
+            self.w * a + other.w * b,
+            self.x * a + other.x * b,
+            self.y * a + other.y * b,
+            self.z * a + other.z * b,
+        )
+        .normalize()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::math::EPSILON;
+    use std::f64::consts::PI;
+
+    fn approx_eq(a: &Mat4, b: &Mat4) {
+        for r in 0..4 {
+            for c in 0..4 {
+                assert!(
+                    (a.get(r, c) - b.get(r, c)).abs() < EPSILON,
+                    "{:?} vs {:?}",
+                    a,
+                    b
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn matrix_round_trip() {
+        let rotations = [
+            Mat4::IDENTITY,
+            Mat4::rotation_x(PI / 3.0),
+            Mat4::rotation_y(PI),
+            Mat4::rotation_z(-PI / 2.0),
+            Mat4::rotation_x(PI) * Mat4::rotation_z(PI / 4.0),
+            Mat4::rotation_y(2.5) * Mat4::rotation_x(-1.0) * Mat4::rotation_z(0.3),
+        ];
+        for m in rotations.iter() {
+            approx_eq(&Quaternion::from_rotation(m).to_matrix(), m);
+        }
+    }
+
+    #[test]
+    fn slerp() {
+        let a = Quaternion::IDENTITY;
+        let b = Quaternion::from_rotation(&Mat4::rotation_y(PI));
+        approx_eq(&a.slerp(&b, 0.0).to_matrix(), &Mat4::IDENTITY);
+        approx_eq(&a.slerp(&b, 0.5).to_matrix(), &Mat4::rotation_y(PI / 2.0));
+        approx_eq(&a.slerp(&b, 1.0).to_matrix(), &Mat4::rotation_y(PI));
+
+        // the shorter way around
+        let b = Quaternion::from_rotation(&Mat4::rotation_z(1.5 * PI));
+        approx_eq(&a.slerp(&b, 0.5).to_matrix(), &Mat4::rotation_z(-PI / 4.0));
+    }
+}
@@ -0,0 +1,577 @@
+//! Wavefront OBJ (and MTL) parsing into renderable triangles
+//!
+
+use crate::color::Color;
+use crate::material::Material;
+use crate::math::vec4::Vec4;
+use crate::shapes::{BoxShape, Group, SmoothTriangle, Triangle};
+
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::path::Path;
+
+#[derive(Debug)]
+pub enum ObjError {
+    Io(std::io::Error),
+    Parse { line: usize, message: &'static str },
+}
+
+impl fmt::Display for ObjError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ObjError::Io(e) => write!(f, "{}", e),
+            ObjError::Parse { line, message } => write!(f, "line {}: {}", line, message),
+        }
+    }
+}
+
+impl std::error::Error for ObjError {}
+
+impl From<std::io::Error> for ObjError {
+    fn from(e: std::io::Error) -> Self {
+        ObjError::Io(e)
+    }
+}
+
+/// Named set of triangles, started by a `g` or `o` statement
+#[derive(Debug, Clone)]
+pub struct ObjGroup {
+    pub name: String,
+    pub triangles: Vec<BoxShape>,
+}
+
+impl ObjGroup {
+    fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            triangles: Vec::new(),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct ObjModel {
+    pub vertices: Vec<Vec4>,
+    pub normals: Vec<Vec4>,
+    pub texture_coords: Vec<(f64, f64)>,
+    /// The first group collects faces declared before any `g`/`o`
+    pub groups: Vec<ObjGroup>,
+    pub material_libraries: Vec<String>,
+    /// Number of non-empty lines that were not understood
+    pub ignored: usize,
+    /// Number of triangles left out for having no area
+    pub degenerate: usize,
+}
+
+impl ObjModel {
+    /// Flattens all groups into shapes ready for `World::add_object`.
+    /// Shadows of a closed transparent mesh need it in one piece,
+    /// see `into_group`.
+    pub fn into_shapes(self) -> Vec<BoxShape> {
+        self.groups
+            .into_iter()
+            .flat_map(|g| g.triangles.into_iter())
+            .collect()
+    }
+
+    /// Builds a single `Group` with one child group per non-empty OBJ group
+    pub fn into_group(self) -> Group {
+        let mut ret = Group::default();
+        for obj_group in self.groups.into_iter() {
+            if obj_group.triangles.is_empty() {
+                continue;
+            }
+            let mut g = Group::default();
+            for t in obj_group.triangles.into_iter() {
+                g.add_child(t);
+            }
+            ret.add_child(Box::new(g));
+        }
+        ret
+    }
+
+    pub fn get_group(&self, name: &str) -> Option<&ObjGroup> {
+        self.groups.iter().find(|g| g.name == name)
+    }
+}
+
+/// Parser keeping track of the materials faces can refer to via `usemtl`
+#[derive(Debug, Default)]
+pub struct ObjParser {
+    materials: HashMap<String, Material>,
+}
+
+impl ObjParser {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add_material(&mut self, name: &str, material: Material) {
+        self.materials.insert(name.to_string(), material);
+    }
+
+    pub fn get_material(&self, name: &str) -> Option<&Material> {
+        self.materials.get(name)
+    }
+
+    /// Reads an OBJ file together with the MTL libraries it references.
+    /// The libraries are looked up relative to the OBJ file.
+    pub fn load(&mut self, path: &Path) -> Result<ObjModel, ObjError> {
+        let input = fs::read_to_string(path)?;
+        let dir = path.parent().unwrap_or_else(|| Path::new(""));
+        for line in input.lines() {
+            let mut tokens = line.split_whitespace();
+            if tokens.next() == Some("mtllib") {
+                for lib in tokens {
+                    let mtl = fs::read_to_string(dir.join(lib))?;
+                    self.parse_mtl(&mtl)?;
+                }
+            }
+        }
+        self.parse(&input)
+    }
+
+    /// Maps MTL statements onto `Material` fields:
+    /// `Kd` is the color, `Ka` and `Ks` are averaged into ambient and specular,
+    /// `Ns` is shininess, `d`/`Tr` give transparency and `Ni` the refractive index.
+    pub fn parse_mtl(&mut self, input: &str) -> Result<(), ObjError> {
+        let mut current: Option<(String, Material)> = None;
+        for (i, line) in input.lines().enumerate() {
+            let line_nb = i + 1;
+            let mut tokens = line.split_whitespace();
+            let keyword = match tokens.next() {
+                Some(k) => k,
+                None => continue,
+            };
+            let args: Vec<&str> = tokens.collect();
+            if keyword == "newmtl" {
+                let name = args.first().ok_or(ObjError::Parse {
+                    line: line_nb,
+                    message: "Missing material name",
+                })?;
+                if let Some((name, material)) = current.take() {
+                    self.materials.insert(name, material);
+                }
+                current = Some((name.to_string(), Material::default()));
+                continue;
+            }
+            let material = match current.as_mut() {
+                Some((_, m)) => m,
+                None => continue,
+            };
+            match keyword {
+                "Kd" => {
+                    let c = parse_floats::<3>(&args, line_nb)?;
+                    material.color = Color::rgb(c[0], c[1], c[2]);
+                }
+                "Ka" => {
+                    let c = parse_floats::<3>(&args, line_nb)?;
+                    material.ambient = (c[0] + c[1] + c[2]) / 3.0;
+                }
+                "Ks" => {
+                    let c = parse_floats::<3>(&args, line_nb)?;
+                    material.specular = (c[0] + c[1] + c[2]) / 3.0;
+                }
+                "Ns" => material.shininess = parse_floats::<1>(&args, line_nb)?[0],
+                "d" => material.transparency = 1.0 - parse_floats::<1>(&args, line_nb)?[0],
+                "Tr" => material.transparency = parse_floats::<1>(&args, line_nb)?[0],
+                "Ni" => material.refractive_index = parse_floats::<1>(&args, line_nb)?[0],
+                _ => {}
+            }
+        }
+        if let Some((name, material)) = current {
+            self.materials.insert(name, material);
+        }
+        Ok(())
+    }
+
+    /// Parses OBJ text. Polygons are fan triangulated, faces with normals
+    /// for every vertex become `SmoothTriangle`s. Triangles with no area
+    /// are left out and counted. Materials named by `usemtl`
+    /// must be registered beforehand, unknown names are an error.
+    pub fn parse(&self, input: &str) -> Result<ObjModel, ObjError> {
+        let mut model = ObjModel {
+            vertices: Vec::new(),
+            normals: Vec::new(),
+            texture_coords: Vec::new(),
+            groups: vec![ObjGroup::new("")],
+            material_libraries: Vec::new(),
+            ignored: 0,
+            degenerate: 0,
+        };
+        let mut material = Material::default();
+
+        for (i, line) in input.lines().enumerate() {
+            let line_nb = i + 1;
+            let line = line.split('#').next().unwrap_or("");
+            let mut tokens = line.split_whitespace();
+            let keyword = match tokens.next() {
+                Some(k) => k,
+                None => continue,
+            };
+            let args: Vec<&str> = tokens.collect();
+            match keyword {
+                "v" => {
+                    let c = parse_floats::<3>(&args, line_nb)?;
+                    model.vertices.push(Vec4::point(c[0], c[1], c[2]));
+                }
+                "vn" => {
+                    let c = parse_floats::<3>(&args, line_nb)?;
+                    let normal = Vec4::vec(c[0], c[1], c[2]);
+                    if normal.magnitude() == 0.0 {
+                        return Err(ObjError::Parse {
+                            line: line_nb,
+                            message: "Normal has zero length",
+                        });
+                    }
+                    model.normals.push(normal.normalize());
+                }
+                "vt" => {
+                    // u [v [w]], the depth w isn't used
+                    if args.len() > 3 {
+                        return Err(ObjError::Parse {
+                            line: line_nb,
+                            message: "Too many values",
+                        });
+                    }
+                    let u = parse_floats::<1>(&args, line_nb)?[0];
+                    let v = match args.get(1) {
+                        Some(v) => parse_float(v, line_nb)?,
+                        None => 0.0,
+                    };
+                    if let Some(w) = args.get(2) {
+                        parse_float(w, line_nb)?;
+                    }
+                    model.texture_coords.push((u, v));
+                }
+                "f" => {
+                    let triangles = Self::parse_face(&model, &args, &material, line_nb)?;
+                    model.degenerate += args.len() - 2 - triangles.len();
+                    let group = model.groups.last_mut().expect("Default group");
+                    group.triangles.extend(triangles);
+                }
+                "g" | "o" => model.groups.push(ObjGroup::new(&args.join(" "))),
+                "usemtl" => {
+                    material = args
+                        .first()
+                        .and_then(|name| self.materials.get(*name))
+                        .cloned()
+                        .ok_or(ObjError::Parse {
+                            line: line_nb,
+                            message: "Unknown material",
+                        })?;
+                }
+                "mtllib" => model
+                    .material_libraries
+                    .extend(args.iter().map(|s| s.to_string())),
+                _ => model.ignored += 1,
+            }
+        }
+
+        Ok(model)
+    }
+
+    fn parse_face(
+        model: &ObjModel,
+        args: &[&str],
+        material: &Material,
+        line: usize,
+    ) -> Result<Vec<BoxShape>, ObjError> {
+        if args.len() < 3 {
+            return Err(ObjError::Parse {
+                line,
+                message: "Face needs at least 3 vertices",
+            });
+        }
+
+        let mut points = Vec::with_capacity(args.len());
+        let mut normals = Vec::with_capacity(args.len());
+        for arg in args {
+            // v, v/vt, v//vn or v/vt/vn
+            let mut refs = arg.split('/');
+            let v = refs.next().unwrap_or("");
+            let _vt = refs.next();
+            let vn = refs.next().filter(|s| !s.is_empty());
+            points.push(*resolve_index(&model.vertices, v, line)?);
+            if let Some(vn) = vn {
+                normals.push(*resolve_index(&model.normals, vn, line)?);
+            }
+        }
+        let smooth = normals.len() == points.len();
+
+        let mut ret: Vec<BoxShape> = Vec::with_capacity(points.len() - 2);
+        for i in 1..points.len() - 1 {
+            let tri = [points[0], points[i], points[i + 1]];
+            if (tri[2] - tri[0]).cross(&(tri[1] - tri[0])).magnitude() == 0.0 {
+                continue;
+            }
+            if smooth {
+                ret.push(SmoothTriangle::new_boxed(
+                    None,
+                    Some(material.clone()),
+                    tri,
+                    [normals[0], normals[i], normals[i + 1]],
+                ));
+            } else {
+                ret.push(Triangle::new_boxed(
+                    None,
+                    Some(material.clone()),
+                    tri[0],
+                    tri[1],
+                    tri[2],
+                ));
+            }
+        }
+        Ok(ret)
+    }
+}
+
+fn parse_floats<const N: usize>(args: &[&str], line: usize) -> Result<[f64; N], ObjError> {
+    let mut ret = [0.0; N];
+    if args.len() < N {
+        return Err(ObjError::Parse {
+            line,
+            message: "Not enough values",
+        });
+    }
+    for (r, a) in ret.iter_mut().zip(args) {
+        *r = parse_float(a, line)?;
+    }
+    Ok(ret)
+}
+
+fn parse_float(arg: &str, line: usize) -> Result<f64, ObjError> {
+    arg.parse().map_err(|_| ObjError::Parse {
+        line,
+        message: "Invalid number",
+    })
+}
+
+/// OBJ indices are 1-based, negative ones count back from the latest entry
+fn resolve_index<'a, T>(items: &'a [T], index: &str, line: usize) -> Result<&'a T, ObjError> {
+    let invalid = ObjError::Parse {
+        line,
+        message: "Invalid face index",
+    };
+    let index: i64 = match index.parse() {
+        Ok(i) => i,
+        Err(_) => return Err(invalid),
+    };
+    let resolved = if index < 0 {
+        items.len() as i64 + index
+    } else {
+        index - 1
+    };
+    if resolved < 0 {
+        return Err(invalid);
+    }
+    items.get(resolved as usize).ok_or(invalid)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn get_triangle(shape: &BoxShape) -> &Triangle {
+        shape.as_any().downcast_ref::<Triangle>().unwrap()
+    }
+
+    #[test]
+    fn ignore_unknown() {
+        let input = "There was a young lady named Bright\n\
+                     who traveled much faster than light.\n\
+                     \n\
+                     # a comment\n\
+                     She set out one day\n";
+        let model = ObjParser::new().parse(input).unwrap();
+        assert_eq!(model.ignored, 3);
+        assert!(model.vertices.is_empty());
+    }
+
+    #[test]
+    fn degenerate_faces() {
+        let inputs = [
+            "v 0 0 0\nv 1 0 0\nv 2 0 0\nf 1 2 3\n",
+            "v 0 1 0\nv -1 0 0\nv 1 0 0\nf 1 1 2 3\n",
+            "v 0 0 0\nv 1 0 0\nv 2 0 0\nvn 0 0 1\nf 1//1 2//1 3//1\n",
+        ];
+        let exps = [(0, 1), (1, 1), (0, 1)];
+        for (input, (count, degenerate)) in inputs.iter().zip(exps.iter()) {
+            let model = ObjParser::new().parse(input).unwrap();
+            assert_eq!(model.groups[0].triangles.len(), *count, "{}", input);
+            assert_eq!(model.degenerate, *degenerate, "{}", input);
+        }
+    }
+
+    #[test]
+    fn vertices() {
+        let input = "v -1 1 0\nv -1.0000 0.5000 0.0000\nv 1 0 0\nv 1 1 0\n";
+        let model = ObjParser::new().parse(input).unwrap();
+        assert_eq!(model.ignored, 0);
+        assert_eq!(model.vertices.len(), 4);
+        assert_eq!(model.vertices[0], Vec4::point(-1.0, 1.0, 0.0));
+        assert_eq!(model.vertices[1], Vec4::point(-1.0, 0.5, 0.0));
+        assert_eq!(model.vertices[3], Vec4::point(1.0, 1.0, 0.0));
+    }
+
+    #[test]
+    fn triangle_faces() {
+        let input = "v -1 1 0\nv -1 0 0\nv 1 0 0\nv 1 1 0\n\nf 1 2 3\nf 1 3 4\n";
+        let model = ObjParser::new().parse(input).unwrap();
+        let g = &model.groups[0];
+        assert_eq!(g.triangles.len(), 2);
+        let t1 = get_triangle(&g.triangles[0]);
+        let t2 = get_triangle(&g.triangles[1]);
+        assert_eq!(t1.p1, model.vertices[0]);
+        assert_eq!(t1.p2, model.vertices[1]);
+        assert_eq!(t1.p3, model.vertices[2]);
+        assert_eq!(t2.p1, model.vertices[0]);
+        assert_eq!(t2.p2, model.vertices[2]);
+        assert_eq!(t2.p3, model.vertices[3]);
+    }
+
+    #[test]
+    fn polygon_fan() {
+        let input = "v -1 1 0\nv -1 0 0\nv 1 0 0\nv 1 1 0\nv 0 2 0\n\nf 1 2 3 4 5\n";
+        let model = ObjParser::new().parse(input).unwrap();
+        let g = &model.groups[0];
+        assert_eq!(g.triangles.len(), 3);
+        let t3 = get_triangle(&g.triangles[2]);
+        assert_eq!(t3.p1, model.vertices[0]);
+        assert_eq!(t3.p2, model.vertices[3]);
+        assert_eq!(t3.p3, model.vertices[4]);
+    }
+
+    #[test]
+    fn named_groups() {
+        let input = "v -1 1 0\nv -1 0 0\nv 1 0 0\nv 1 1 0\n\
+                     g FirstGroup\nf 1 2 3\no SecondGroup\nf 1 3 4\n";
+        let model = ObjParser::new().parse(input).unwrap();
+        assert_eq!(model.groups.len(), 3);
+        assert!(model.groups[0].triangles.is_empty());
+        assert_eq!(model.get_group("FirstGroup").unwrap().triangles.len(), 1);
+        assert_eq!(model.get_group("SecondGroup").unwrap().triangles.len(), 1);
+        assert_eq!(model.into_shapes().len(), 2);
+    }
+
+    #[test]
+    fn into_group() {
+        let input = "v -1 1 0\nv -1 0 0\nv 1 0 0\nv 1 1 0\n\
+                     f 1 2 3\ng FirstGroup\nf 1 2 3\nf 1 3 4\ng Empty\n";
+        let model = ObjParser::new().parse(input).unwrap();
+        let g = model.into_group();
+        let children = g.get_children();
+        assert_eq!(children.len(), 2);
+        let first = children[1].as_any().downcast_ref::<Group>().unwrap();
+        assert_eq!(first.get_children().len(), 2);
+    }
+
+    #[test]
+    fn normals_and_texture_coords() {
+        let input = "vn 0 0 1\nvn 0.707 0 -0.707\nvn 1 2 3\nvt 0.5 0.25\nvt 0.75\nvt 0.1 0.2 0.3\n";
+        let model = ObjParser::new().parse(input).unwrap();
+        assert_eq!(model.normals.len(), 3);
+        assert_eq!(model.normals[0], Vec4::VEC_Z_ONE);
+        assert_eq!(model.normals[1], Vec4::vec(0.707, 0.0, -0.707).normalize());
+        assert_eq!(model.normals[2], Vec4::vec(1.0, 2.0, 3.0).normalize());
+        assert_eq!(
+            model.texture_coords,
+            vec![(0.5, 0.25), (0.75, 0.0), (0.1, 0.2)]
+        );
+    }
+
+    #[test]
+    fn malformed_vertex_data() {
+        let inputs = [
+            "vn 0 0 1\nvn 0 0 0\n",
+            "vt\n",
+            "vt 0.1 0.2 0.3 0.4\n",
+            "vt 0.1 x\n",
+            "vt 0.1 0.2 x\n",
+        ];
+        let lines = [2, 1, 1, 1, 1];
+        for (input, exp) in inputs.iter().zip(lines) {
+            match ObjParser::new().parse(input) {
+                Err(ObjError::Parse { line, .. }) => assert_eq!(line, exp, "{}", input),
+                _ => panic!("Expected parse error for {:?}", input),
+            }
+        }
+    }
+
+    #[test]
+    fn faces_with_normals() {
+        let input = "v 0 1 0\nv -1 0 0\nv 1 0 0\nvn -1 0 0\nvn 1 0 0\nvn 0 1 0\n\
+                     f 1//3 2//1 3//2\nf 1/0/3 2/102/1 3/14/2\n";
+        let model = ObjParser::new().parse(input).unwrap();
+        let g = &model.groups[0];
+        assert_eq!(g.triangles.len(), 2);
+        for shape in g.triangles.iter() {
+            let t = shape.as_any().downcast_ref::<SmoothTriangle>().unwrap();
+            assert_eq!(t.p1, model.vertices[0]);
+            assert_eq!(t.p2, model.vertices[1]);
+            assert_eq!(t.p3, model.vertices[2]);
+            assert_eq!(t.n1, model.normals[2]);
+            assert_eq!(t.n2, model.normals[0]);
+            assert_eq!(t.n3, model.normals[1]);
+        }
+    }
+
+    #[test]
+    fn negative_indices() {
+        let input = "v -1 1 0\nv -1 0 0\nv 1 0 0\nf -3 -2 -1\n";
+        let model = ObjParser::new().parse(input).unwrap();
+        let t = get_triangle(&model.groups[0].triangles[0]);
+        assert_eq!(t.p1, model.vertices[0]);
+        assert_eq!(t.p3, model.vertices[2]);
+    }
+
+    #[test]
+    fn malformed_faces() {
+        let inputs = [
+            "v -1 1 0\nv -1 0 0\nf 1 2\n",
+            "v -1 1 0\nv -1 0 0\nv 1 0 0\n\nf 1 2 4\n",
+            "v -1 1 0\nv -1 0 0\nv 1 0 0\nf 1 x 3\n",
+            "v -1 1 0\nv -1 0 0\nv 1 0 0\nf 0 1 2\n",
+        ];
+        let lines = [3, 5, 4, 4];
+        for (input, exp) in inputs.iter().zip(lines) {
+            match ObjParser::new().parse(input) {
+                Err(ObjError::Parse { line, .. }) => assert_eq!(line, exp, "{}", input),
+                _ => panic!("Expected parse error for {:?}", input),
+            }
+        }
+    }
+
+    #[test]
+    fn materials() {
+        let mtl = "newmtl red\nKd 1 0 0\nKs 0.3 0.3 0.3\nNs 10\n\
+                   newmtl glass\nd 0.25\nNi 1.5\n";
+        let mut parser = ObjParser::new();
+        parser.parse_mtl(mtl).unwrap();
+        let red = parser.get_material("red").unwrap();
+        assert_eq!(red.color, Color::RED);
+        assert!((red.specular - 0.3).abs() < 1e-9);
+        assert_eq!(red.shininess, 10.0);
+        let glass = parser.get_material("glass").unwrap();
+        assert_eq!(glass.transparency, 0.75);
+        assert_eq!(glass.refractive_index, 1.5);
+
+        let input = "mtllib scene.mtl\nv -1 1 0\nv -1 0 0\nv 1 0 0\n\
+                     usemtl red\nf 1 2 3\nf 1 2 3\n";
+        let model = parser.parse(input).unwrap();
+        assert_eq!(model.material_libraries, vec!["scene.mtl".to_string()]);
+        let g = &model.groups[0];
+        assert_eq!(g.triangles[0].get_material(), red);
+        assert_eq!(g.triangles[1].get_material(), red);
+
+        let input = "v -1 1 0\nv -1 0 0\nv 1 0 0\nusemtl red\nf 1 2 3\nusemtl unknown\n";
+        match parser.parse(input) {
+            Err(ObjError::Parse { line, message }) => {
+                assert_eq!(line, 6);
+                assert_eq!(message, "Unknown material");
+            }
+            _ => panic!("Expected unknown material error"),
+        }
+    }
+}
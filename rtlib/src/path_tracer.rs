@@ -0,0 +1,346 @@
+//! Monte Carlo path tracing.
+//!
+//! An alternative to the recursive `World::color_at`, which follows only
+//! mirror reflections and refractions. Here each hit continues along a
+//! single randomly chosen direction, so light bounced off diffuse surfaces
+//! (color bleeding, indirect lighting) is accounted for as well.
+//! Averaging many samples per pixel is needed for a clean image.
+
+use crate::color::Color;
+use crate::math::vec4::Vec4;
+use crate::precompute::PreCompute;
+use crate::ray::Ray;
+use crate::util::rng::Rng;
+use crate::world::World;
+
+use std::f64::consts::PI;
+
+/// Unidirectional path tracer.
+/// Lights are sampled directly at every hit (next event estimation) with
+/// the material's own shading model, the path then continues in a cosine
+/// weighted direction, a mirror reflection or a refraction, picked at
+/// random by the material.
+/// The ambient term of materials is ignored, the real indirect light
+/// replaces it.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PathTracer {
+    /// Bounces after which the path is cut off regardless
+    pub max_depth: u32,
+    /// Bounces before Russian roulette may end the path.
+    /// Past it, dim paths are likely to be stopped and the surviving ones
+    /// are brightened to make up for it.
+    pub roulette_depth: u32,
+}
+
+impl Default for PathTracer {
+    fn default() -> Self {
+        Self {
+            max_depth: 8,
+            roulette_depth: 3,
+        }
+    }
+}
+
+impl PathTracer {
+    /// One sample of the light arriving along the ray
+    pub fn trace(&self, world: &World, ray: &Ray, rng: &mut Rng) -> Color {
+        let mut color = Color::BLACK;
+        let mut throughput = Color::WHITE;
+        let mut ray = Ray::new_with_time(&ray.origin, &ray.direction, ray.time);
+
+        for depth in 0..=self.max_depth {
+            let xs = world.intersect(&ray);
+            let hit = match xs.hit() {
+                Some(hit) => hit,
+                None => break,
+            };
+            let comps = PreCompute::new(hit, &ray, Some(xs.get_inner_ref()));
+            let material = comps.get_material();
+            // leaving a transparent object, the light was absorbed on the way
+            if comps.is_inside() {
+                let distance = hit.t * ray.direction.magnitude();
+                throughput = throughput * material.absorption_over(distance);
+            }
+
+            color = color + throughput * (material.emissive + direct_light(world, &comps));
+
+            // Pick how the path continues, in proportion to the weight of
+            // each part of the surface. The chosen part is divided by its
+            // probability, so the estimate stays unbiased.
+            // Like `World::shade_hit`, Fresnel splits the light between
+            // reflection and refraction of surfaces doing both.
+            let mut reflect = material.reflectivness.max(0.0);
+            let mut refract = material.transparency.max(0.0);
+            if comps.is_reflective_and_transparent() {
+                let reflectance = comps.schlick();
+                reflect *= reflectance;
+                refract *= 1.0 - reflectance;
+            }
+            let albedo = comps.surface_color() * material.diffuse;
+            let diffuse = max_channel(&albedo);
+            let total = reflect + refract + diffuse;
+            if total <= 0.0 || depth == self.max_depth {
+                break;
+            }
+            let choice = rng.next_f64() * total;
+            let (next, weight) = if choice < reflect {
+                (mirror_ray(&comps), Color::WHITE * total)
+            } else if choice < reflect + refract {
+                match comps.get_refracted_ray() {
+                    Some(r) => (r, Color::WHITE * total),
+                    // total internal reflection, nothing gets through
+                    None => break,
+                }
+            } else {
+                let direction = cosine_hemisphere(comps.get_normal(), rng);
+                (
+                    Ray::new_with_time(comps.get_overpoint(), &direction, comps.get_time()),
+                    albedo * (total / diffuse),
+                )
+            };
+            throughput = throughput * weight;
+            ray = next;
+
+            if depth + 1 >= self.roulette_depth {
+                let survival = max_channel(&throughput).min(0.95);
+                if survival <= 0.0 || rng.next_f64() >= survival {
+                    break;
+                }
+                throughput = throughput / survival;
+            }
+        }
+        color
+    }
+}
+
+/// Light arriving directly from the world's lights.
+/// Matches `World::shade_hit` without the ambient term.
+fn direct_light(world: &World, comps: &PreCompute) -> Color {
+    let mut ret = Color::BLACK;
+    for light in world.lights.iter() {
+        let visible =
+            world.intensity_at_time(light.as_ref(), comps.get_overpoint(), comps.get_time());
+        ret = ret + comps.direct_lighting(light.as_ref(), visible);
+    }
+    ret
+}
+
+fn mirror_ray(comps: &PreCompute) -> Ray {
+    Ray::new_with_time(
+        comps.get_overpoint(),
+        comps.get_reflect_vec(),
+        comps.get_time(),
+    )
+}
+
+fn max_channel(c: &Color) -> f64 {
+    c.r.max(c.g).max(c.b)
+}
+
+/// Random direction on the hemisphere around `normal`,
+/// more likely the closer it is to the normal (cosine weighted)
+pub fn cosine_hemisphere(normal: &Vec4, rng: &mut Rng) -> Vec4 {
+    let phi = 2.0 * PI * rng.next_f64();
+    let r2 = rng.next_f64();
+    let r = r2.sqrt();
+    let (x, y, z) = (r * phi.cos(), r * phi.sin(), (1.0 - r2).sqrt());
+
+    let helper = if normal.x.abs() > 0.9 {
+        Vec4::VEC_Y_ONE
+    } else {
+        Vec4::VEC_X_ONE
+    };
+    let tangent = helper.cross(normal).normalize();
+    let bitangent = normal.cross(&tangent);
+    (tangent * x + bitangent * y + *normal * z).normalize()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::light::PointLight;
+    use crate::material::{Material, ShadingModel};
+    use crate::math::matrix::Mat4;
+    use crate::shapes::{Plane, Sphere};
+
+    #[test]
+    fn cosine_hemisphere_samples() {
+        let mut rng = Rng::new(7);
+        let normals = [
+            Vec4::VEC_Y_ONE,
+            Vec4::VEC_X_ONE,
+            Vec4::vec(1.0, -2.0, 3.0).normalize(),
+        ];
+        for n in normals.iter() {
+            let mut sum = 0.0;
+            for _ in 0..10000 {
+                let d = cosine_hemisphere(n, &mut rng);
+                assert!((d.magnitude() - 1.0).abs() < 1e-9);
+                let cos = d.dot(n);
+                assert!(cos >= 0.0, "Failed for {:?}", n);
+                sum += cos;
+            }
+            // mean cosine of a cosine weighted hemisphere is 2/3
+            assert!(
+                (sum / 10000.0 - 2.0 / 3.0).abs() < 0.02,
+                "Failed for {:?}",
+                n
+            );
+        }
+    }
+
+    #[test]
+    fn miss_is_black() {
+        let w = World::default();
+        let r = Ray::new(&Vec4::point(0.0, 0.0, -5.0), &Vec4::VEC_Y_ONE);
+        let mut rng = Rng::new(0);
+        assert_eq!(PathTracer::default().trace(&w, &r, &mut rng), Color::BLACK);
+    }
+
+    #[test]
+    fn direct_matches_phong_diffuse() {
+        // nothing to bounce off, so only the direct light is left
+        let mut w = World::new();
+        w.add_light(PointLight::new_boxed(
+            Vec4::point(-10.0, 10.0, -10.0),
+            Color::WHITE,
+        ));
+        let mut material = Material::default();
+        material.color = Color::rgb(0.8, 1.0, 0.6);
+        material.ambient = 0.0;
+        material.specular = 0.0;
+        w.add_object(Sphere::new_boxed(None, Some(material)));
+        let r = Ray::new(&Vec4::point(0.0, 0.0, -5.0), &Vec4::VEC_Z_ONE);
+        let mut rng = Rng::new(3);
+        let traced = PathTracer::default().trace(&w, &r, &mut rng);
+        assert_eq!(traced, w.color_at(&r, 5));
+        assert_ne!(traced, Color::BLACK);
+    }
+
+    #[test]
+    fn direct_matches_phong_specular() {
+        let mut w = World::new();
+        w.add_light(PointLight::new_boxed(
+            Vec4::point(0.0, 0.0, -10.0),
+            Color::WHITE,
+        ));
+        let mut material = Material::default();
+        material.ambient = 0.0;
+        material.specular = 0.9;
+        w.add_object(Sphere::new_boxed(None, Some(material.clone())));
+        let r = Ray::new(&Vec4::point(0.0, 0.0, -5.0), &Vec4::VEC_Z_ONE);
+        let mut rng = Rng::new(3);
+        let traced = PathTracer::default().trace(&w, &r, &mut rng);
+        assert_eq!(traced, w.color_at(&r, 5));
+        // the highlight faces the eye
+        assert_eq!(traced, Color::rgb(1.8, 1.8, 1.8));
+
+        material.shading = ShadingModel::Microfacet {
+            roughness: 0.3,
+            metalness: 1.0,
+        };
+        w.objects[0].set_material(material);
+        let traced = PathTracer::default().trace(&w, &r, &mut rng);
+        assert_eq!(traced, w.color_at(&r, 5));
+        assert_ne!(traced, Color::BLACK);
+    }
+
+    #[test]
+    fn glass_weighted_like_whitted() {
+        // reflective glass, the eye sees a lit wall behind it reflected
+        let mut w = World::new();
+        w.add_light(PointLight::new_boxed(
+            Vec4::point(0.0, 0.0, -8.0),
+            Color::WHITE,
+        ));
+        let mut wall = Material::default();
+        wall.ambient = 0.0;
+        wall.specular = 0.0;
+        w.add_object(Plane::new_boxed(
+            Some(Mat4::translation(0.0, 0.0, -10.0) * Mat4::rotation_x(PI / 2.0)),
+            Some(wall),
+        ));
+        let mut glass = Material::GLASS;
+        glass.diffuse = 0.0;
+        glass.specular = 0.0;
+        glass.ambient = 0.0;
+        glass.reflectivness = 1.0;
+        glass.transparency = 1.0;
+        w.add_object(Sphere::new_boxed(None, Some(glass)));
+        let r = Ray::new(&Vec4::point(0.0, 0.0, -5.0), &Vec4::VEC_Z_ONE);
+        let exp = w.color_at(&r, 5);
+
+        let tracer = PathTracer {
+            max_depth: 5,
+            roulette_depth: 5,
+        };
+        let mut rng = Rng::new(5);
+        let mut color = Color::BLACK;
+        for _ in 0..2000 {
+            color = color + tracer.trace(&w, &r, &mut rng);
+        }
+        let color = color / 2000.0;
+        assert!(exp.r > 0.01);
+        assert!((color.r - exp.r).abs() < 0.01, "{:?} vs {:?}", color, exp);
+    }
+
+    #[test]
+    fn emissive_surface() {
+        let mut w = World::new();
+        let mut material = Material::default();
+        material.diffuse = 0.0;
+        material.emissive = Color::rgb(0.2, 0.4, 0.6);
+        w.add_object(Sphere::new_boxed(None, Some(material)));
+        let r = Ray::new(&Vec4::point(0.0, 0.0, -5.0), &Vec4::VEC_Z_ONE);
+        let mut rng = Rng::new(0);
+        let traced = PathTracer::default().trace(&w, &r, &mut rng);
+        assert_eq!(traced, Color::rgb(0.2, 0.4, 0.6));
+    }
+
+    #[test]
+    fn color_bleeding() {
+        // white floor next to a glowing red wall, no lights at all
+        let mut w = World::new();
+        let mut floor = Material::default();
+        floor.color = Color::WHITE;
+        floor.ambient = 0.0;
+        floor.specular = 0.0;
+        w.add_object(Plane::new_boxed(None, Some(floor)));
+        let mut wall = Material::default();
+        wall.diffuse = 0.0;
+        wall.emissive = Color::rgb(1.0, 0.0, 0.0);
+        w.add_object(Plane::new_boxed(
+            Some(Mat4::translation(1.0, 0.0, 0.0) * Mat4::rotation_z(PI / 2.0)),
+            Some(wall),
+        ));
+        let r = Ray::new(&Vec4::point(0.5, 1.0, -1.0), &Vec4::vec(0.0, -1.0, 1.0));
+        assert_eq!(w.color_at(&r, 5), Color::BLACK);
+
+        let tracer = PathTracer::default();
+        let mut rng = Rng::new(11);
+        let mut color = Color::BLACK;
+        for _ in 0..200 {
+            color = color + tracer.trace(&w, &r, &mut rng);
+        }
+        let color = color / 200.0;
+        assert!(color.r > 0.1, "{:?}", color);
+        assert_eq!(color.g, 0.0);
+        assert_eq!(color.b, 0.0);
+    }
+
+    #[test]
+    fn deterministic() {
+        let mut w = World::default();
+        w.add_object(Plane::new_boxed(
+            Some(Mat4::translation(0.0, -1.0, 0.0)),
+            None,
+        ));
+        let r = Ray::new(&Vec4::point(0.0, 0.5, -5.0), &Vec4::vec(0.0, -0.2, 1.0));
+        let tracer = PathTracer::default();
+        for seed in 0..20 {
+            let a = tracer.trace(&w, &r, &mut Rng::new(seed));
+            let b = tracer.trace(&w, &r, &mut Rng::new(seed));
+            assert_eq!(a, b);
+        }
+    }
+}
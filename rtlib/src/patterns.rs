@@ -18,7 +18,7 @@ pub use ring::RingPattern;
 pub mod checkers;
 pub use checkers::CheckersPattern;
 
-pub trait Pattern: Any + Debug {
+pub trait Pattern: Any + Debug + Send + Sync {
     fn as_any(&self) -> &dyn Any;
     fn box_clone(&self) -> BoxPattern;
     fn box_eq(&self, other: &dyn Any) -> bool;
@@ -31,7 +31,7 @@ pub trait Pattern: Any + Debug {
     fn local_pattern_at(&self, local_point: Vec4) -> Color;
 
     fn pattern_at(&self, object: &dyn Shape, world_point: Vec4) -> Color {
-        let object_point = object.inverse_transformation() * world_point;
+        let object_point = object.world_to_object(world_point);
         let local_point = self.inverse_transformation() * object_point;
         self.local_pattern_at(local_point)
     }
@@ -187,4 +187,19 @@ pub mod tests {
             Color::rgb(0.75, 0.5, 0.25)
         );
     }
+
+    #[test]
+    fn testpattern_color_at_grouped() {
+        let p = TestPattern::default_boxed();
+        let mut g = shapes::Group::new(Some(Mat4::scaling(2.0, 2.0, 2.0)));
+        g.add_child(shapes::Sphere::new_boxed(
+            Some(Mat4::translation(1.0, 0.0, 0.0)),
+            None,
+        ));
+        let s = &g.get_children()[0];
+        assert_eq!(
+            p.pattern_at(s.as_ref(), Vec4::point(4.0, 6.0, 8.0)),
+            Color::rgb(1.0, 3.0, 4.0)
+        );
+    }
 }
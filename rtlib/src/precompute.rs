@@ -1,6 +1,6 @@
 use crate::color::Color;
 use crate::intersection::Intersection;
-use crate::light::PointLight;
+use crate::light::Light;
 use crate::material::Material;
 use crate::math::vec4::Vec4;
 use crate::math::EPSILON;
@@ -14,18 +14,19 @@ pub struct PreCompute {
     _point: Vec4,
     eye_vec: Vec4,
     normal: Vec4,
-    _inside: bool,
+    inside: bool,
     over_point: Vec4,
     under_point: Vec4,
     reflect_vec: Vec4,
     n1: f64,
     n2: f64,
+    time: f64,
 }
 
 impl PreCompute {
     pub fn new(i: &Intersection, r: &Ray, xs: Option<&Vec<Intersection>>) -> Self {
         let p = r.position(i.t);
-        let mut normal = i.object.normal_at(p);
+        let mut normal = i.object.normal_at(p, i);
         let e = -r.direction;
         let mut inside = false;
         if normal.dot(&e) < 0.0 {
@@ -70,37 +71,75 @@ impl PreCompute {
             _point: p,
             eye_vec: e,
             normal,
-            _inside: inside,
+            inside,
             over_point: p + (normal * EPSILON),
             under_point: p - (normal * EPSILON),
             reflect_vec: r.direction.reflect(&normal),
             n1: n1,
             n2: n2,
+            time: r.time,
         }
     }
 
-    pub fn lighting(&self, light: &PointLight, in_shadow: bool) -> Color {
+    pub fn lighting(&self, light: &dyn Light, light_intensity: Color) -> Color {
         self.object.get_material().lighting(
             &*(self.object),
             &self.over_point,
             light,
             &self.eye_vec,
             &self.normal,
-            in_shadow,
+            light_intensity,
         )
     }
 
+    /// `lighting` without the ambient part
+    pub fn direct_lighting(&self, light: &dyn Light, light_intensity: Color) -> Color {
+        self.object.get_material().direct_lighting(
+            &*(self.object),
+            &self.over_point,
+            light,
+            &self.eye_vec,
+            &self.normal,
+            light_intensity,
+        )
+    }
+
+    /// Ambient part of `lighting`
+    pub fn ambient(&self, light: &dyn Light) -> Color {
+        self.surface_color() * light.get_intensity() * self.get_material().ambient
+    }
+
     pub fn get_overpoint(&self) -> &Vec4 {
         &self.over_point
     }
 
+    pub fn get_normal(&self) -> &Vec4 {
+        &self.normal
+    }
+
     pub fn get_reflect_vec(&self) -> &Vec4 {
         &self.reflect_vec
     }
 
+    /// Time of the ray that hit, for the rays spawned from the hit
+    pub fn get_time(&self) -> f64 {
+        self.time
+    }
+
+    /// Whether the ray hit the surface from inside the object
+    pub fn is_inside(&self) -> bool {
+        self.inside
+    }
+
     pub fn get_material(&self) -> &Material {
         self.object.get_material()
     }
+
+    /// Color of the material (or its pattern) at the hit
+    pub fn surface_color(&self) -> Color {
+        self.get_material()
+            .color_at(&*self.object, &self.over_point)
+    }
     pub fn is_reflective_and_transparent(&self) -> bool {
         self.object.get_material().is_reflective_and_transparent()
     }
@@ -115,7 +154,7 @@ impl PreCompute {
         }
         let cos_t = (1.0 - sin2_t).sqrt();
         let direction = self.normal * (n_ratio * cos_i - cos_t) - self.eye_vec * n_ratio;
-        Some(Ray::new(&self.under_point, &direction))
+        Some(Ray::new_with_time(&self.under_point, &direction, self.time))
     }
 
     /// Schlick's adaptation of Fresnel Effect for reflectance
@@ -154,7 +193,7 @@ mod tests {
         assert_eq!(comps._point, Vec4::point(0.0, 0.0, -1.0));
         assert_eq!(comps.eye_vec, Vec4::vec(0.0, 0.0, -1.0));
         assert_eq!(comps.normal, Vec4::vec(0.0, 0.0, -1.0));
-        assert_eq!(comps._inside, false);
+        assert_eq!(comps.inside, false);
 
         let r = Ray::new(&Vec4::POINT_ZERO, &Vec4::vec(0.0, 0.0, 1.0));
 
@@ -166,7 +205,7 @@ mod tests {
         assert_eq!(comps._point, Vec4::point(0.0, 0.0, 1.0));
         assert_eq!(comps.eye_vec, Vec4::vec(0.0, 0.0, -1.0));
         assert_eq!(comps.normal, Vec4::vec(0.0, 0.0, -1.0));
-        assert_eq!(comps._inside, true);
+        assert_eq!(comps.inside, true);
     }
 
     #[test]
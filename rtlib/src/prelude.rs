@@ -1,17 +1,19 @@
 // structs
-pub use crate::camera::Camera;
+pub use crate::camera::{Camera, Projection};
 pub use crate::canvas::Canvas;
 pub use crate::color::Color;
 pub use crate::intersection::Intersection;
-pub use crate::light::PointLight;
-pub use crate::material::Material;
+pub use crate::light::{AreaLight, Attenuation, DirectionalLight, PointLight, SpotLight};
+pub use crate::material::{Material, ShadingModel};
 pub use crate::math::matrix::Mat4;
 pub use crate::math::vec4::Vec4;
+pub use crate::obj::ObjParser;
+pub use crate::path_tracer::PathTracer;
 pub use crate::patterns;
 pub use crate::ray::Ray;
-pub use crate::render::RenderSettings;
+pub use crate::render::{AdaptiveSampling, Integrator, RenderSettings};
 pub use crate::shapes;
-pub use crate::world::World;
+pub use crate::world::{AmbientOcclusion, World};
 
 // Traits
 // pub use crate::patterns::Pattern;
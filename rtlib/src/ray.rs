@@ -4,13 +4,21 @@ use crate::math::vec4::Vec4;
 pub struct Ray {
     pub origin: Vec4,
     pub direction: Vec4,
+    /// Moment the ray is cast at, moving shapes are placed according to it.
+    /// Rays spawned at a hit (reflections, shadows, ...) keep it.
+    pub time: f64,
 }
 
 impl Ray {
     pub fn new(origin: &Vec4, direction: &Vec4) -> Self {
+        Self::new_with_time(origin, direction, 0.0)
+    }
+
+    pub fn new_with_time(origin: &Vec4, direction: &Vec4, time: f64) -> Self {
         Self {
             origin: *origin,
             direction: *direction,
+            time,
         }
     }
 
@@ -21,7 +29,11 @@ impl Ray {
     pub fn transform(&self, m: &Mat4) -> Self {
         let origin = m * self.origin;
         let direction = m * self.direction;
-        Self { origin, direction }
+        Self {
+            origin,
+            direction,
+            time: self.time,
+        }
     }
 
     /// A helper function to reduce duplication
@@ -44,6 +56,9 @@ mod tests {
         let ray = Ray::new(&origin, &direction);
         assert_eq!(origin, ray.origin);
         assert_eq!(direction, ray.direction);
+        assert_eq!(0.0, ray.time);
+        let ray = Ray::new_with_time(&origin, &direction, 0.25);
+        assert_eq!(0.25, ray.time);
     }
 
     #[test]
@@ -67,5 +82,8 @@ mod tests {
         let ret = r.transform(&scale);
         assert_eq!(Vec4::point(2.0, 6.0, 12.0), ret.origin);
         assert_eq!(Vec4::vec(0.0, 3.0, 0.0), ret.direction);
+
+        let r = Ray::new_with_time(&Vec4::point(1.0, 2.0, 3.0), &Vec4::VEC_Y_ONE, 0.5);
+        assert_eq!(0.5, r.transform(&transl).time);
     }
 }
@@ -1,40 +1,368 @@
 use crate::camera::Camera;
 use crate::canvas::Canvas;
-use crate::world::World;
+use crate::color::Color;
+use crate::path_tracer::PathTracer;
+use crate::util::rng::Rng;
+use crate::world::{AmbientOcclusion, World};
+
+use std::thread;
 
 pub struct RenderSettings {
     pub recursion_limit: u32,
+    /// Number of threads rendering the image, `1` renders on the calling thread
+    pub threads: usize,
+    /// Rays averaged per pixel. A single ray goes through the pixel center,
+    /// more are jittered within a grid of cells covering the pixel.
+    /// Counts that aren't square use `n` rows and `n` columns instead,
+    /// one sample in each (n-rooks).
+    pub samples_per_pixel: u32,
+    /// Refines only the high contrast pixels instead of sampling
+    /// every pixel uniformly. Takes precedence over `samples_per_pixel`.
+    pub adaptive: Option<AdaptiveSampling>,
+    pub integrator: Integrator,
 }
 
 impl Default for RenderSettings {
     fn default() -> Self {
-        Self { recursion_limit: 5 }
+        Self {
+            recursion_limit: 5,
+            threads: thread::available_parallelism().map_or(1, |n| n.get()),
+            samples_per_pixel: 1,
+            adaptive: None,
+            integrator: Integrator::default(),
+        }
     }
 }
 
-pub fn render(camera: &Camera, world: &World, settings: &RenderSettings) -> Canvas {
-    let w = camera.get_width();
-    let h = camera.get_height();
-    let mut image = Canvas::new(w, h);
+/// How the color seen along a camera ray is computed
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum Integrator {
+    /// Recursive reflections and refractions, limited by `recursion_limit`
+    #[default]
+    Whitted,
+    /// Monte Carlo path tracing, noisy unless many samples per pixel are taken
+    PathTracing(PathTracer),
+    /// Grayscale ambient occlusion only, for compositing
+    AmbientOcclusion(AmbientOcclusion),
+}
+
+/// Adaptive anti-aliasing.
+/// Pixels are rendered with a single ray first. A pixel whose color
+/// differs from any of its neighbours by more than `threshold` is then
+/// sampled at its corners, and quarters of it keep being subdivided
+/// while their corners differ, up to `max_depth` times.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AdaptiveSampling {
+    /// Largest difference of a color channel that isn't refined
+    pub threshold: f64,
+    /// `2` gives at most a 4x4 grid of quarters per pixel
+    pub max_depth: u32,
+}
 
-    for y in 0..h {
-        for x in 0..w {
-            let r = camera.ray_for_pixel(x, y);
-            let color = world.color_at(&r, settings.recursion_limit);
-            let _ = image.put_pixel(x, y, color);
+impl Default for AdaptiveSampling {
+    fn default() -> Self {
+        Self {
+            threshold: 0.1,
+            max_depth: 2,
         }
     }
+}
 
+/// Renders the world as seen by the camera.
+/// Rows are interleaved between the threads, so each gets a similar
+/// share of the scene. Every pixel is computed the same way regardless
+/// of the thread count, so the output doesn't depend on it.
+pub fn render(camera: &Camera, world: &World, settings: &RenderSettings) -> Canvas {
+    let mut image = Canvas::new(camera.get_width(), camera.get_height());
+    match settings.adaptive {
+        None => render_rows(&mut image, settings.threads, |y| {
+            (0..camera.get_width())
+                .map(|x| render_pixel(camera, world, settings, x, y))
+                .collect()
+        }),
+        Some(adaptive) => {
+            render_rows(&mut image, settings.threads, |y| {
+                (0..camera.get_width())
+                    .map(|x| {
+                        let mut rng = Rng::for_pixel(x, y);
+                        sample(camera, world, settings, &mut rng, x, y, 0.5, 0.5)
+                    })
+                    .collect()
+            });
+            let first_pass = image.clone();
+            render_rows(&mut image, settings.threads, |y| {
+                (0..camera.get_width())
+                    .map(|x| {
+                        let color = first_pass.get_pixel(x, y).unwrap();
+                        if needs_refinement(&first_pass, x, y, adaptive.threshold) {
+                            refine_pixel(camera, world, settings, &adaptive, x, y)
+                        } else {
+                            color
+                        }
+                    })
+                    .collect()
+            });
+        }
+    }
     image
 }
 
+/// Fills every row of the image with `row_colors`, spread over `threads`
+fn render_rows<F>(image: &mut Canvas, threads: usize, row_colors: F)
+where
+    F: Fn(u32) -> Vec<Color> + Sync,
+{
+    let h = image.get_height();
+    let threads = threads.clamp(1, (h as usize).max(1));
+
+    if threads == 1 {
+        for y in 0..h {
+            put_row(image, y, row_colors(y));
+        }
+        return;
+    }
+
+    let row_colors = &row_colors;
+    thread::scope(|s| {
+        let handles: Vec<_> = (0..threads)
+            .map(|first| {
+                s.spawn(move || {
+                    (first as u32..h)
+                        .step_by(threads)
+                        .map(|y| (y, row_colors(y)))
+                        .collect::<Vec<_>>()
+                })
+            })
+            .collect();
+        for handle in handles {
+            for (y, row) in handle.join().expect("Render thread panicked") {
+                put_row(image, y, row);
+            }
+        }
+    });
+}
+
+#[allow(clippy::too_many_arguments)]
+fn sample(
+    camera: &Camera,
+    world: &World,
+    settings: &RenderSettings,
+    rng: &mut Rng,
+    x: u32,
+    y: u32,
+    offset_x: f64,
+    offset_y: f64,
+) -> Color {
+    let mut r = if camera.get_aperture() > 0.0 {
+        camera.ray_through_lens(x, y, offset_x, offset_y, rng.next_f64(), rng.next_f64())
+    } else {
+        camera.ray_for_pixel(x, y, offset_x, offset_y)
+    };
+    let (open, close) = camera.get_shutter();
+    if close > open {
+        r.time = camera.time_at(rng.next_f64());
+    }
+    match settings.integrator {
+        Integrator::Whitted => world.color_at(&r, settings.recursion_limit),
+        Integrator::PathTracing(tracer) => tracer.trace(world, &r, rng),
+        Integrator::AmbientOcclusion(ao) => world.occlusion_at(&r, &ao),
+    }
+}
+
+fn render_pixel(
+    camera: &Camera,
+    world: &World,
+    settings: &RenderSettings,
+    x: u32,
+    y: u32,
+) -> Color {
+    let samples = settings.samples_per_pixel.max(1);
+    let mut rng = Rng::for_pixel(x, y);
+    if samples == 1 {
+        return sample(camera, world, settings, &mut rng, x, y, 0.5, 0.5);
+    }
+
+    let mut color = Color::BLACK;
+    for (offset_x, offset_y) in stratified_offsets(samples, &mut rng) {
+        color = color + sample(camera, world, settings, &mut rng, x, y, offset_x, offset_y);
+    }
+    color / samples as f64
+}
+
+/// `n` jittered offsets within a pixel.
+/// Square counts get one sample per cell of a grid. Otherwise the pixel
+/// is cut into `n` columns and `n` rows and every column and every row
+/// gets exactly one sample (n-rooks), so any count covers it evenly.
+fn stratified_offsets(n: u32, rng: &mut Rng) -> Vec<(f64, f64)> {
+    let side = (n as f64).sqrt().round() as u32;
+    if side * side == n {
+        let size = side as f64;
+        return (0..n)
+            .map(|i| {
+                (
+                    ((i % side) as f64 + rng.next_f64()) / size,
+                    ((i / side) as f64 + rng.next_f64()) / size,
+                )
+            })
+            .collect();
+    }
+
+    // shuffled rows, Fisher-Yates
+    let mut rows: Vec<u32> = (0..n).collect();
+    for i in (1..n as usize).rev() {
+        let j = (rng.next_u64() % (i as u64 + 1)) as usize;
+        rows.swap(i, j);
+    }
+    let size = n as f64;
+    rows.iter()
+        .enumerate()
+        .map(|(column, row)| {
+            (
+                (column as f64 + rng.next_f64()) / size,
+                (*row as f64 + rng.next_f64()) / size,
+            )
+        })
+        .collect()
+}
+
+/// Largest difference between the channels of two colors
+fn contrast(a: &Color, b: &Color) -> f64 {
+    (a.r - b.r)
+        .abs()
+        .max((a.g - b.g).abs())
+        .max((a.b - b.b).abs())
+}
+
+fn needs_refinement(image: &Canvas, x: u32, y: u32, threshold: f64) -> bool {
+    let color = image.get_pixel(x, y).unwrap();
+    let neighbours = [
+        (x.wrapping_sub(1), y),
+        (x + 1, y),
+        (x, y.wrapping_sub(1)),
+        (x, y + 1),
+    ];
+    neighbours.iter().any(|(nx, ny)| {
+        image
+            .get_pixel(*nx, *ny)
+            .is_ok_and(|n| contrast(&color, &n) > threshold)
+    })
+}
+
+fn refine_pixel(
+    camera: &Camera,
+    world: &World,
+    settings: &RenderSettings,
+    adaptive: &AdaptiveSampling,
+    x: u32,
+    y: u32,
+) -> Color {
+    // the first pass used the pixel's own seed, continue from another one
+    let mut rng = Rng::new(!(((y as u64) << 32) | x as u64));
+    let mut refine = |ox: f64, oy: f64| sample(camera, world, settings, &mut rng, x, y, ox, oy);
+    let corners = [(0.0, 0.0), (1.0, 0.0), (0.0, 1.0), (1.0, 1.0)].map(|(ox, oy)| refine(ox, oy));
+    subdivide(&mut refine, adaptive, (0.0, 0.0), 1.0, corners, 0)
+}
+
+/// Average color of the square at `origin` with side `size`, in pixel units.
+/// `corners` are ordered top left, top right, bottom left, bottom right.
+/// The whole pixel is always split once, as its corners alone could all
+/// miss whatever made it stand out from its neighbours.
+fn subdivide<F>(
+    sample: &mut F,
+    adaptive: &AdaptiveSampling,
+    origin: (f64, f64),
+    size: f64,
+    corners: [Color; 4],
+    depth: u32,
+) -> Color
+where
+    F: FnMut(f64, f64) -> Color,
+{
+    let average = (corners[0] + corners[1] + corners[2] + corners[3]) / 4.0;
+    let uniform = corners
+        .iter()
+        .all(|c| contrast(c, &average) <= adaptive.threshold);
+    if depth >= adaptive.max_depth || (depth > 0 && uniform) {
+        return average;
+    }
+
+    let (x0, y0) = origin;
+    let half = size / 2.0;
+    let (xm, ym) = (x0 + half, y0 + half);
+    let (x1, y1) = (x0 + size, y0 + size);
+    let top = sample(xm, y0);
+    let left = sample(x0, ym);
+    let center = sample(xm, ym);
+    let right = sample(x1, ym);
+    let bottom = sample(xm, y1);
+
+    let quarters = [
+        ((x0, y0), [corners[0], top, left, center]),
+        ((xm, y0), [top, corners[1], center, right]),
+        ((x0, ym), [left, center, corners[2], bottom]),
+        ((xm, ym), [center, right, bottom, corners[3]]),
+    ];
+    let mut color = Color::BLACK;
+    for (origin, corners) in quarters {
+        color = color + subdivide(sample, adaptive, origin, half, corners, depth + 1);
+    }
+    color / 4.0
+}
+
+fn put_row(image: &mut Canvas, y: u32, row: Vec<Color>) {
+    for (x, color) in row.into_iter().enumerate() {
+        let _ = image.put_pixel(x as u32, y, color);
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::color::Color;
+    use crate::light::PointLight;
+    use crate::material::Material;
+    use crate::math::matrix::Mat4;
     use crate::math::vec4::Vec4;
+    use crate::math::EPSILON;
+    use crate::shapes::{Moving, Plane, Sphere};
     use std::f64::consts::PI;
 
+    #[test]
+    fn stratified_offsets_cover_pixel() {
+        for n in [2, 3, 5] {
+            let mut sum = (0.0, 0.0);
+            let pixels = 1000;
+            for i in 0..pixels {
+                let offsets = stratified_offsets(n, &mut Rng::for_pixel(i, 0));
+                assert_eq!(offsets.len(), n as usize);
+                // one sample in every column and every row
+                let mut columns = vec![0; n as usize];
+                let mut rows = vec![0; n as usize];
+                for (ox, oy) in offsets.iter() {
+                    columns[(ox * n as f64) as usize] += 1;
+                    rows[(oy * n as f64) as usize] += 1;
+                    sum = (sum.0 + ox, sum.1 + oy);
+                }
+                assert!(columns.iter().all(|c| *c == 1), "Failed for {}", n);
+                assert!(rows.iter().all(|c| *c == 1), "Failed for {}", n);
+            }
+            let count = (pixels * n) as f64;
+            assert!((sum.0 / count - 0.5).abs() < 0.01, "Failed for {}", n);
+            assert!((sum.1 / count - 0.5).abs() < 0.01, "Failed for {}", n);
+        }
+
+        // square counts use a grid, one sample per cell
+        for n in [4, 9] {
+            let side = (n as f64).sqrt() as usize;
+            let offsets = stratified_offsets(n, &mut Rng::for_pixel(3, 4));
+            let mut cells = vec![0; n as usize];
+            for (ox, oy) in offsets.iter() {
+                let cell = (oy * side as f64) as usize * side + (ox * side as f64) as usize;
+                cells[cell] += 1;
+            }
+            assert!(cells.iter().all(|c| *c == 1), "Failed for {}", n);
+        }
+    }
+
     #[test]
     fn basic_render() {
         let w = World::default();
@@ -42,11 +370,351 @@ mod tests {
         let from = Vec4::point(0.0, 0.0, -5.0);
         let to = Vec4::POINT_ZERO;
         let up = Vec4::VEC_Y_ONE;
-        c.view_transform(&from, &to, &up);
-        let image: Canvas = render(&c, &w, &RenderSettings { recursion_limit: 0 });
+        c.view_transform(&from, &to, &up).unwrap();
+        let settings = RenderSettings {
+            recursion_limit: 0,
+            ..Default::default()
+        };
+        let image: Canvas = render(&c, &w, &settings);
         assert_eq!(
             image.get_pixel(5, 5).unwrap(),
             Color::rgb(0.38066, 0.47583, 0.2855)
         )
     }
+
+    #[test]
+    fn threaded_render_matches_serial() {
+        let mut w = World::default();
+        let mut material = Material::GLASS;
+        material.reflectivness = 0.5;
+        w.add_object(Sphere::new_boxed(
+            Some(Mat4::translation(0.5, 0.5, -2.0) * Mat4::scaling(0.5, 0.5, 0.5)),
+            Some(material),
+        ));
+        w.add_object(Plane::new_boxed(
+            Some(Mat4::translation(0.0, -1.0, 0.0)),
+            None,
+        ));
+        let mut c = Camera::new(21, 13, PI / 2.0);
+        c.view_transform(
+            &Vec4::point(0.0, 1.0, -5.0),
+            &Vec4::POINT_ZERO,
+            &Vec4::VEC_Y_ONE,
+        )
+        .unwrap();
+
+        let serial = render(
+            &c,
+            &w,
+            &RenderSettings {
+                threads: 1,
+                ..Default::default()
+            },
+        );
+        for threads in [2, 3, 8, 64] {
+            let settings = RenderSettings {
+                threads,
+                ..Default::default()
+            };
+            let image = render(&c, &w, &settings);
+            for y in 0..13 {
+                for x in 0..21 {
+                    let a = serial.get_pixel(x, y).unwrap();
+                    let b = image.get_pixel(x, y).unwrap();
+                    assert!(
+                        a.r.to_bits() == b.r.to_bits()
+                            && a.g.to_bits() == b.g.to_bits()
+                            && a.b.to_bits() == b.b.to_bits(),
+                        "Pixel {} {} differs with {} threads",
+                        x,
+                        y,
+                        threads
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn supersampling() {
+        let mut w = World::new();
+        w.add_light(PointLight::new_boxed(
+            Vec4::point(-10.0, 10.0, -10.0),
+            Color::WHITE,
+        ));
+        let mut material = Material::default();
+        material.ambient = 1.0;
+        material.diffuse = 0.0;
+        material.specular = 0.0;
+        w.add_object(Sphere::new_boxed(None, Some(material)));
+        // the sphere covers only the middle of the center pixel
+        let mut c = Camera::new(3, 3, PI / 2.0);
+        c.view_transform(
+            &Vec4::point(0.0, 0.0, -5.0),
+            &Vec4::POINT_ZERO,
+            &Vec4::VEC_Y_ONE,
+        )
+        .unwrap();
+
+        let mut settings = RenderSettings {
+            recursion_limit: 0,
+            threads: 1,
+            samples_per_pixel: 1,
+            adaptive: None,
+            integrator: Integrator::Whitted,
+        };
+        let single = render(&c, &w, &settings);
+        settings.samples_per_pixel = 16;
+        let sampled = render(&c, &w, &settings);
+        settings.threads = 3;
+        let threaded = render(&c, &w, &settings);
+
+        for y in 0..3 {
+            for x in 0..3 {
+                let s = sampled.get_pixel(x, y).unwrap();
+                assert_eq!(s, threaded.get_pixel(x, y).unwrap());
+                assert!(s.r >= 0.0 && s.r <= 1.0);
+            }
+        }
+        // missed pixels are unchanged, the center one is partially covered
+        assert_eq!(single.get_pixel(0, 0).unwrap(), Color::BLACK);
+        assert_eq!(sampled.get_pixel(0, 0).unwrap(), Color::BLACK);
+        assert_eq!(single.get_pixel(1, 1).unwrap(), Color::WHITE);
+        let center = sampled.get_pixel(1, 1).unwrap();
+        assert!(center.r > 0.0 && center.r < 1.0, "{:?}", center);
+    }
+
+    fn edge_scene() -> (World, Camera) {
+        let mut w = World::new();
+        w.add_light(PointLight::new_boxed(
+            Vec4::point(-10.0, 10.0, -10.0),
+            Color::WHITE,
+        ));
+        let mut material = Material::default();
+        material.ambient = 1.0;
+        material.diffuse = 0.0;
+        material.specular = 0.0;
+        w.add_object(Sphere::new_boxed(None, Some(material)));
+        let mut c = Camera::new(5, 5, PI / 2.0);
+        c.view_transform(
+            &Vec4::point(0.0, 0.0, -4.0),
+            &Vec4::POINT_ZERO,
+            &Vec4::VEC_Y_ONE,
+        )
+        .unwrap();
+        (w, c)
+    }
+
+    #[test]
+    fn adaptive() {
+        let (w, c) = edge_scene();
+        let mut settings = RenderSettings {
+            recursion_limit: 0,
+            threads: 1,
+            samples_per_pixel: 1,
+            adaptive: None,
+            integrator: Integrator::Whitted,
+        };
+        let single = render(&c, &w, &settings);
+        settings.adaptive = Some(AdaptiveSampling::default());
+        let adaptive = render(&c, &w, &settings);
+        settings.threads = 4;
+        let threaded = render(&c, &w, &settings);
+
+        let mut partial = 0;
+        for y in 0..5 {
+            for x in 0..5 {
+                let a = adaptive.get_pixel(x, y).unwrap();
+                assert_eq!(a, threaded.get_pixel(x, y).unwrap());
+                assert!(a.r >= 0.0 && a.r <= 1.0);
+                if a.r > 0.0 && a.r < 1.0 {
+                    partial += 1;
+                }
+            }
+        }
+        assert!(partial > 0);
+        // far corner has only black neighbours
+        assert_eq!(adaptive.get_pixel(0, 0).unwrap(), Color::BLACK);
+
+        // nothing exceeds the threshold
+        settings.adaptive = Some(AdaptiveSampling {
+            threshold: 1.0,
+            max_depth: 2,
+        });
+        let unrefined = render(&c, &w, &settings);
+        for y in 0..5 {
+            for x in 0..5 {
+                assert_eq!(
+                    unrefined.get_pixel(x, y).unwrap(),
+                    single.get_pixel(x, y).unwrap()
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn subdivide_edge() {
+        let adaptive = AdaptiveSampling {
+            threshold: 0.1,
+            max_depth: 2,
+        };
+        // white left of x = 0.3
+        let color = |x: f64| {
+            if x < 0.3 {
+                Color::WHITE
+            } else {
+                Color::BLACK
+            }
+        };
+        let mut count = 0;
+        let mut sample = |x: f64, _y: f64| {
+            count += 1;
+            color(x)
+        };
+        let corners = [color(0.0), color(1.0), color(0.0), color(1.0)];
+        let c = subdivide(&mut sample, &adaptive, (0.0, 0.0), 1.0, corners, 0);
+        // corners of the right half agree, only the left half is subdivided again
+        assert_eq!(count, 5 + 2 * 5);
+        assert!((c.r - 0.375).abs() < EPSILON);
+
+        let mut count = 0;
+        let mut sample = |_x: f64, _y: f64| {
+            count += 1;
+            Color::WHITE
+        };
+        let c = subdivide(
+            &mut sample,
+            &adaptive,
+            (0.0, 0.0),
+            1.0,
+            [Color::WHITE; 4],
+            0,
+        );
+        assert_eq!(count, 5);
+        assert_eq!(c, Color::WHITE);
+    }
+
+    #[test]
+    fn path_tracing() {
+        let mut w = World::default();
+        w.add_object(Plane::new_boxed(
+            Some(Mat4::translation(0.0, -1.0, 0.0)),
+            None,
+        ));
+        let mut c = Camera::new(9, 7, PI / 2.0);
+        c.view_transform(
+            &Vec4::point(0.0, 1.0, -5.0),
+            &Vec4::POINT_ZERO,
+            &Vec4::VEC_Y_ONE,
+        )
+        .unwrap();
+        let mut settings = RenderSettings {
+            threads: 1,
+            samples_per_pixel: 4,
+            integrator: Integrator::PathTracing(PathTracer::default()),
+            ..Default::default()
+        };
+        let serial = render(&c, &w, &settings);
+        settings.threads = 3;
+        let threaded = render(&c, &w, &settings);
+        let whitted = render(
+            &c,
+            &w,
+            &RenderSettings {
+                samples_per_pixel: 4,
+                ..Default::default()
+            },
+        );
+        let mut differs = false;
+        for y in 0..7 {
+            for x in 0..9 {
+                let a = serial.get_pixel(x, y).unwrap();
+                assert_eq!(a, threaded.get_pixel(x, y).unwrap());
+                differs |= a != whitted.get_pixel(x, y).unwrap();
+            }
+        }
+        assert!(differs);
+    }
+
+    #[test]
+    fn ambient_occlusion_pass() {
+        let mut w = World::default();
+        w.add_object(Plane::new_boxed(
+            Some(Mat4::translation(0.0, -1.0, 0.0)),
+            None,
+        ));
+        let mut c = Camera::new(9, 7, PI / 2.0);
+        c.view_transform(
+            &Vec4::point(0.0, 1.0, -5.0),
+            &Vec4::POINT_ZERO,
+            &Vec4::VEC_Y_ONE,
+        )
+        .unwrap();
+        let settings = RenderSettings {
+            integrator: Integrator::AmbientOcclusion(AmbientOcclusion {
+                samples: 32,
+                distance: 2.0,
+            }),
+            ..Default::default()
+        };
+        let image = render(&c, &w, &settings);
+        let mut occluded = false;
+        for y in 0..7 {
+            for x in 0..9 {
+                let p = image.get_pixel(x, y).unwrap();
+                assert!(p.r == p.g && p.g == p.b && (0.0..=1.0).contains(&p.r));
+                occluded |= p.r < 1.0;
+            }
+        }
+        assert!(occluded);
+        // the sky
+        assert_eq!(image.get_pixel(0, 0).unwrap(), Color::WHITE);
+    }
+
+    #[test]
+    fn motion_blur() {
+        let mut w = World::new();
+        w.add_light(PointLight::default_boxed());
+        let mut material = Material::default();
+        material.ambient = 1.0;
+        material.diffuse = 0.0;
+        material.specular = 0.0;
+        w.add_object(Moving::new_boxed(
+            Mat4::IDENTITY,
+            Mat4::translation(3.0, 0.0, 0.0),
+            Sphere::new_boxed(None, Some(material)),
+        ));
+        let mut c = Camera::new(9, 9, PI / 2.0);
+        c.view_transform(
+            &Vec4::point(0.0, 0.0, -5.0),
+            &Vec4::POINT_ZERO,
+            &Vec4::VEC_Y_ONE,
+        )
+        .unwrap();
+        let mut settings = RenderSettings {
+            threads: 1,
+            samples_per_pixel: 16,
+            ..Default::default()
+        };
+        let frozen = render(&c, &w, &settings);
+        c.set_shutter(0.0, 1.0);
+        let blurred = render(&c, &w, &settings);
+        settings.threads = 4;
+        let threaded = render(&c, &w, &settings);
+
+        // the sphere starts in the middle and moves to the right of the image
+        assert_eq!(frozen.get_pixel(4, 4).unwrap(), Color::WHITE);
+        assert_eq!(frozen.get_pixel(6, 4).unwrap(), Color::BLACK);
+        let trail = blurred.get_pixel(6, 4).unwrap();
+        assert!(trail.r > 0.0 && trail.r < 1.0, "{:?}", trail);
+        assert_eq!(blurred.get_pixel(2, 4).unwrap(), Color::BLACK);
+        for y in 0..9 {
+            for x in 0..9 {
+                assert_eq!(
+                    blurred.get_pixel(x, y).unwrap(),
+                    threaded.get_pixel(x, y).unwrap()
+                );
+            }
+        }
+    }
 }
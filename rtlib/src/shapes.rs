@@ -13,7 +13,23 @@ pub use cylinder::Cylinder;
 pub mod cone;
 pub use cone::Cone;
 
-use crate::intersection::Intersections;
+pub mod triangle;
+pub use triangle::Triangle;
+
+pub mod smooth_triangle;
+pub use smooth_triangle::SmoothTriangle;
+
+pub mod group;
+pub use group::Group;
+
+pub mod csg;
+pub use csg::{Csg, CsgOp};
+
+pub mod moving;
+pub use moving::Moving;
+
+use crate::bounds::BoundingBox;
+use crate::intersection::{Intersection, Intersections};
 use crate::material::Material;
 use crate::math::matrix::Mat4;
 use crate::math::vec4::Vec4;
@@ -21,8 +37,9 @@ use crate::ray::Ray;
 
 use std::any::Any;
 use std::fmt::Debug;
+use std::sync::Arc;
 
-pub trait Shape: Any + Debug {
+pub trait Shape: Any + Debug + Send + Sync {
     fn as_any(&self) -> &dyn Any;
     fn box_clone(&self) -> BoxShape;
     fn box_eq(&self, other: &dyn Any) -> bool;
@@ -30,26 +47,117 @@ pub trait Shape: Any + Debug {
     fn set_material(&mut self, material: Material);
     fn get_material(&self) -> &Material;
 
+    fn get_parent(&self) -> Option<&Arc<Parent>>;
+    fn set_parent(&mut self, parent: Option<Arc<Parent>>);
+
     fn local_intersect(&self, local_ray: Ray) -> Intersections;
-    fn local_normal_at(&self, local_point: Vec4) -> Vec4;
+    fn local_normal_at(&self, local_point: Vec4, hit: &Intersection) -> Vec4;
 
     fn transform(&mut self, m: &Mat4);
     fn set_transform(&mut self, transformation: Mat4);
     fn transformation(&self) -> &Mat4;
     fn inverse_transformation(&self) -> &Mat4;
 
+    /// Bounds of the shape in its own object space
+    fn bounds(&self) -> BoundingBox;
+
+    /// Bounds of the shape in its parent's space, i.e. with its transform applied
+    fn parent_space_bounds(&self) -> BoundingBox {
+        self.bounds().transform(self.transformation())
+    }
+
+    /// Builds a bounding volume hierarchy below the shape,
+    /// so that no leaf holds more than `threshold` shapes where possible.
+    /// Only aggregate shapes like groups have anything to divide.
+    fn divide(&mut self, _threshold: usize) {}
+
+    /// Whether `other` is this shape or one of its descendants
+    fn includes(&self, other: &dyn Shape) -> bool {
+        self.box_eq(other.as_any())
+    }
+
     fn intersect(&self, world_ray: &Ray) -> Intersections {
         self.local_intersect(world_ray.transform(self.inverse_transformation()))
     }
 
-    fn normal_at(&self, world_point: Vec4) -> Vec4 {
-        let object_normal = self.local_normal_at(self.inverse_transformation() * world_point);
-        let mut world_normal = self.inverse_transformation().transpose() * object_normal;
-        world_normal.w = 0.0;
-        world_normal.normalize()
+    /// Whether the ray hits the shape at any `t` in `[0, max_t)`
+    fn intersects_within(&self, ray: &Ray, max_t: f64) -> bool {
+        self.intersect(ray)
+            .get_inner_ref()
+            .iter()
+            .any(|i| i.t >= 0.0 && i.t < max_t)
+    }
+
+    /// Whether the ray hits an opaque part of the shape that casts shadows
+    /// at any `t` in `[0, max_t)`
+    fn blocks_light_within(&self, ray: &Ray, max_t: f64) -> bool {
+        self.intersect(ray).get_inner_ref().iter().any(|i| {
+            let material = i.object.get_material();
+            i.t >= 0.0 && i.t < max_t && material.casts_shadow && material.transparency <= 0.0
+        })
+    }
+
+    /// Converts a point from world space to object space,
+    /// passing through every group the shape is part of.
+    fn world_to_object(&self, world_point: Vec4) -> Vec4 {
+        let point = match self.get_parent() {
+            Some(parent) => parent.world_to_object(world_point),
+            None => world_point,
+        };
+        self.inverse_transformation() * point
+    }
+
+    /// Converts a normal from object space to world space,
+    /// passing through every group the shape is part of.
+    fn normal_to_world(&self, object_normal: Vec4) -> Vec4 {
+        let normal = to_parent_space(self.inverse_transformation(), object_normal);
+        match self.get_parent() {
+            Some(parent) => parent.normal_to_world(normal),
+            None => normal,
+        }
+    }
+
+    /// `hit` is the intersection that produced `world_point`,
+    /// so shapes like smooth triangles can use its `u`/`v`.
+    fn normal_at(&self, world_point: Vec4, hit: &Intersection) -> Vec4 {
+        let local_point = self.world_to_object(world_point);
+        let local_normal = self.local_normal_at(local_point, hit);
+        self.normal_to_world(local_normal)
+    }
+}
+
+/// What a shape needs to know about the group containing it.
+/// Links up through the enclosing groups until the world is reached.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Parent {
+    pub inverse_transform: Mat4,
+    pub parent: Option<Arc<Parent>>,
+}
+
+impl Parent {
+    pub fn world_to_object(&self, world_point: Vec4) -> Vec4 {
+        let point = match &self.parent {
+            Some(parent) => parent.world_to_object(world_point),
+            None => world_point,
+        };
+        self.inverse_transform * point
+    }
+
+    pub fn normal_to_world(&self, normal: Vec4) -> Vec4 {
+        let normal = to_parent_space(&self.inverse_transform, normal);
+        match &self.parent {
+            Some(parent) => parent.normal_to_world(normal),
+            None => normal,
+        }
     }
 }
 
+fn to_parent_space(inverse_transform: &Mat4, normal: Vec4) -> Vec4 {
+    let mut normal = inverse_transform.transpose() * normal;
+    normal.w = 0.0;
+    normal.normalize()
+}
+
 pub type BoxShape = Box<dyn Shape>;
 
 impl Clone for BoxShape {
@@ -67,7 +175,6 @@ impl PartialEq for BoxShape {
 #[cfg(test)]
 mod testshape {
     use super::*;
-    use crate::intersection::Intersection;
     use crate::math::EPSILON;
     use crate::util::uid;
 
@@ -77,6 +184,7 @@ mod testshape {
         pub transform: Mat4,
         pub inverse_transform: Mat4,
         pub material: Material,
+        parent: Option<Arc<Parent>>,
     }
 
     impl PartialEq for TestShape {
@@ -105,6 +213,13 @@ mod testshape {
             &self.material
         }
 
+        fn get_parent(&self) -> Option<&Arc<Parent>> {
+            self.parent.as_ref()
+        }
+        fn set_parent(&mut self, parent: Option<Arc<Parent>>) {
+            self.parent = parent;
+        }
+
         fn transform(&mut self, m: &Mat4) {
             self.transform = m * self.transform;
         }
@@ -119,7 +234,11 @@ mod testshape {
             &self.inverse_transform
         }
 
-        fn local_normal_at(&self, _local_point: Vec4) -> Vec4 {
+        fn bounds(&self) -> BoundingBox {
+            BoundingBox::new(Vec4::point(-1.0, -1.0, -1.0), Vec4::point(1.0, 1.0, 1.0))
+        }
+
+        fn local_normal_at(&self, _local_point: Vec4, _hit: &Intersection) -> Vec4 {
             unimplemented!()
         }
         fn local_intersect(&self, _local_ray: Ray) -> Intersections {
@@ -136,6 +255,7 @@ mod testshape {
                 transform,
                 material: material.unwrap_or_default(),
                 inverse_transform,
+                parent: None,
             }
         }
 
@@ -155,6 +275,7 @@ mod testshape {
                 transform: Mat4::default(),
                 inverse_transform: Mat4::default(),
                 material: Material::default(),
+                parent: None,
             }
         }
     }
@@ -184,4 +305,15 @@ mod tests {
         let object = testshape::TestShape::new(None, Some(m.clone()));
         assert_eq!(object.material, m);
     }
+
+    #[test]
+    fn testshape_parent_space_bounds() {
+        let object = testshape::TestShape::new(
+            Some(Mat4::translation(1.0, -3.0, 5.0) * Mat4::scaling(0.5, 2.0, 4.0)),
+            None,
+        );
+        let b = object.parent_space_bounds();
+        assert_eq!(b.min, Vec4::point(0.5, -5.0, 1.0));
+        assert_eq!(b.max, Vec4::point(1.5, -1.0, 9.0));
+    }
 }
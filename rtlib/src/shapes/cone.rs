@@ -1,14 +1,16 @@
+use crate::bounds::BoundingBox;
 use crate::intersection::{Intersection, Intersections};
 use crate::material::Material;
 use crate::math::matrix::Mat4;
 use crate::math::vec4::Vec4;
 use crate::math::EPSILON;
 use crate::ray::Ray;
-use crate::shapes::{BoxShape, Shape};
+use crate::shapes::{BoxShape, Parent, Shape};
 use crate::util::uid;
 
 use std::any::Any;
 use std::fmt::Debug;
+use std::sync::Arc;
 
 #[derive(Debug, Clone)]
 pub struct Cone {
@@ -16,6 +18,7 @@ pub struct Cone {
     pub transform: Mat4,
     pub inverse_transform: Mat4,
     pub material: Material,
+    parent: Option<Arc<Parent>>,
     pub limit_y: (f64, f64),
     pub closed: bool,
 }
@@ -46,6 +49,13 @@ impl Shape for Cone {
         &self.material
     }
 
+    fn get_parent(&self) -> Option<&Arc<Parent>> {
+        self.parent.as_ref()
+    }
+    fn set_parent(&mut self, parent: Option<Arc<Parent>>) {
+        self.parent = parent;
+    }
+
     fn transform(&mut self, m: &Mat4) {
         self.transform = m * self.transform;
     }
@@ -60,7 +70,15 @@ impl Shape for Cone {
         &self.inverse_transform
     }
 
-    fn local_normal_at(&self, local_point: Vec4) -> Vec4 {
+    fn bounds(&self) -> BoundingBox {
+        let r = self.limit_y.0.abs().max(self.limit_y.1.abs());
+        BoundingBox::new(
+            Vec4::point(-r, self.limit_y.0, -r),
+            Vec4::point(r, self.limit_y.1, r),
+        )
+    }
+
+    fn local_normal_at(&self, local_point: Vec4, _hit: &Intersection) -> Vec4 {
         let mut y = (local_point.x.powi(2) + local_point.z.powi(2)).sqrt();
         if y < self.limit_y.1.abs() && local_point.y >= self.limit_y.1 - EPSILON {
             Vec4::VEC_Y_ONE
@@ -142,6 +160,7 @@ impl Cone {
             transform,
             material: material.unwrap_or_default(),
             inverse_transform,
+            parent: None,
             limit_y,
             closed,
         }
@@ -186,6 +205,7 @@ impl Default for Cone {
             transform: Mat4::default(),
             inverse_transform: Mat4::default(),
             material: Material::default(),
+            parent: None,
             limit_y: (-f64::INFINITY, f64::INFINITY),
             closed: false,
         }
@@ -270,6 +290,7 @@ mod tests {
     #[test]
     fn normal_at() {
         let cone = Cone::default();
+        let hit = Intersection::new(cone.box_clone(), 0.0);
         let points = [
             Vec4::POINT_ZERO,
             Vec4::point(1.0, 1.0, 1.0),
@@ -281,7 +302,7 @@ mod tests {
             Vec4::vec(-1.0, 1.0, 0.0),
         ];
         for (p, en) in izip!(&points, &expected_normals) {
-            let n = cone.local_normal_at(*p);
+            let n = cone.local_normal_at(*p, &hit);
             assert_eq!(n, *en);
         }
     }
@@ -290,8 +311,22 @@ mod tests {
     fn normal_at_distant_cap() {
         let mut cone = Cone::default();
         cone.limit_y = (0.0, 2.0);
+        let hit = Intersection::new(cone.box_clone(), 0.0);
         let p = Vec4::point(1.5, 2.0, 0.0);
-        let n = cone.local_normal_at(p);
+        let n = cone.local_normal_at(p, &hit);
         dbg!(n);
     }
+
+    #[test]
+    fn bounds() {
+        let c = Cone::default();
+        let b = c.bounds();
+        assert!(b.min.x.is_infinite() && b.min.x < 0.0);
+        assert!(b.max.y.is_infinite() && b.max.y > 0.0);
+
+        let c = Cone::new(None, None, Some((-5.0, 3.0)), false);
+        let b = c.bounds();
+        assert_eq!(b.min, Vec4::point(-5.0, -5.0, -5.0));
+        assert_eq!(b.max, Vec4::point(5.0, 3.0, 5.0));
+    }
 }
@@ -0,0 +1,349 @@
+use crate::bounds::BoundingBox;
+use crate::intersection::{Intersection, Intersections};
+use crate::material::Material;
+use crate::math::matrix::Mat4;
+use crate::math::vec4::Vec4;
+use crate::ray::Ray;
+use crate::shapes::{BoxShape, Parent, Shape};
+use crate::util::uid;
+
+use std::any::Any;
+use std::fmt::Debug;
+use std::sync::Arc;
+
+/// Operation combining the two sides of a [`Csg`]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CsgOp {
+    /// Everything inside either of the shapes
+    Union,
+    /// Only what is inside both shapes
+    Intersection,
+    /// The left shape with the right one carved out of it
+    Difference,
+}
+
+impl CsgOp {
+    /// Whether a hit is part of the combined surface.
+    /// `left_hit` tells which side was hit, `in_left` and `in_right`
+    /// whether the hit lies inside the left and right shapes.
+    pub fn intersection_allowed(&self, left_hit: bool, in_left: bool, in_right: bool) -> bool {
+        match self {
+            Self::Union => (left_hit && !in_right) || (!left_hit && !in_left),
+            Self::Intersection => (left_hit && in_right) || (!left_hit && in_left),
+            Self::Difference => (left_hit && !in_right) || (!left_hit && in_left),
+        }
+    }
+}
+
+/// *Constructive solid geometry* node, combines two shapes with a [`CsgOp`].
+/// Either side can be any shape, including groups and other CSG nodes.
+#[derive(Debug, Clone)]
+pub struct Csg {
+    uid: usize,
+    pub transform: Mat4,
+    pub inverse_transform: Mat4,
+    pub material: Material,
+    parent: Option<Arc<Parent>>,
+    op: CsgOp,
+    left: BoxShape,
+    right: BoxShape,
+}
+
+impl PartialEq for Csg {
+    fn eq(&self, other: &Self) -> bool {
+        self.uid == other.uid
+            && self.transform == other.transform
+            && self.material == other.material
+    }
+}
+
+impl Shape for Csg {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+    fn box_clone(&self) -> BoxShape {
+        Box::new((*self).clone())
+    }
+    fn box_eq(&self, other: &dyn Any) -> bool {
+        other.downcast_ref::<Self>().map_or(false, |a| self == a)
+    }
+
+    /// Sets the material of the node and both of its sides
+    fn set_material(&mut self, material: Material) {
+        self.left.set_material(material.clone());
+        self.right.set_material(material.clone());
+        self.material = material;
+    }
+    fn get_material(&self) -> &Material {
+        &self.material
+    }
+
+    fn get_parent(&self) -> Option<&Arc<Parent>> {
+        self.parent.as_ref()
+    }
+    fn set_parent(&mut self, parent: Option<Arc<Parent>>) {
+        self.parent = parent;
+        self.update_children();
+    }
+
+    fn transform(&mut self, m: &Mat4) {
+        self.set_transform(m * self.transform);
+    }
+    fn set_transform(&mut self, transform: Mat4) {
+        self.transform = transform;
+        self.inverse_transform = self.transform.inverse().unwrap();
+        self.update_children();
+    }
+    fn transformation(&self) -> &Mat4 {
+        &self.transform
+    }
+    fn inverse_transformation(&self) -> &Mat4 {
+        &self.inverse_transform
+    }
+
+    /// Bounds of both sides, even though difference and intersection
+    /// may only ever need a part of it
+    fn bounds(&self) -> BoundingBox {
+        let mut ret = self.left.parent_space_bounds();
+        ret.merge(&self.right.parent_space_bounds());
+        ret
+    }
+
+    fn divide(&mut self, threshold: usize) {
+        self.left.divide(threshold);
+        self.right.divide(threshold);
+    }
+
+    fn includes(&self, other: &dyn Shape) -> bool {
+        self.left.includes(other) || self.right.includes(other)
+    }
+
+    fn local_normal_at(&self, _local_point: Vec4, _hit: &Intersection) -> Vec4 {
+        unreachable!("Csg has no surface of its own, normals come from its children")
+    }
+
+    fn local_intersect(&self, local_ray: Ray) -> Intersections {
+        let mut ret = Intersections::new();
+        if !self.bounds().intersects(&local_ray) {
+            return ret;
+        }
+        ret.append(&mut self.left.intersect(&local_ray));
+        ret.append(&mut self.right.intersect(&local_ray));
+        ret.sort();
+        self.filter_intersections(ret)
+    }
+}
+
+impl Csg {
+    pub fn new(transform: Option<Mat4>, op: CsgOp, left: BoxShape, right: BoxShape) -> Self {
+        let transform = transform.unwrap_or_default();
+        let inverse_transform = transform.inverse().unwrap();
+        let mut ret = Self {
+            uid: uid::fetch_uid(),
+            transform,
+            material: Material::default(),
+            inverse_transform,
+            parent: None,
+            op,
+            left,
+            right,
+        };
+        ret.update_children();
+        ret
+    }
+
+    pub fn new_boxed(
+        transform: Option<Mat4>,
+        op: CsgOp,
+        left: BoxShape,
+        right: BoxShape,
+    ) -> BoxShape {
+        Box::new(Self::new(transform, op, left, right))
+    }
+
+    pub fn get_op(&self) -> CsgOp {
+        self.op
+    }
+
+    pub fn get_left(&self) -> &BoxShape {
+        &self.left
+    }
+
+    pub fn get_right(&self) -> &BoxShape {
+        &self.right
+    }
+
+    /// Keeps only the hits that are part of the combined surface.
+    /// `xs` has to be sorted.
+    pub fn filter_intersections(&self, mut xs: Intersections) -> Intersections {
+        let mut in_left = false;
+        let mut in_right = false;
+        xs.retain(|i| {
+            let left_hit = self.left.includes(i.object.as_ref());
+            let allowed = self.op.intersection_allowed(left_hit, in_left, in_right);
+            if left_hit {
+                in_left = !in_left;
+            } else {
+                in_right = !in_right;
+            }
+            allowed
+        });
+        xs
+    }
+
+    fn as_parent(&self) -> Arc<Parent> {
+        Arc::new(Parent {
+            inverse_transform: self.inverse_transform,
+            parent: self.parent.clone(),
+        })
+    }
+
+    /// Same as for groups, both sides hold a copy of the parent chain
+    fn update_children(&mut self) {
+        let parent = self.as_parent();
+        self.left.set_parent(Some(parent.clone()));
+        self.right.set_parent(Some(parent));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::shapes::{Cube, Group, Sphere};
+    use itertools::izip;
+
+    #[test]
+    fn basic() {
+        let s = Sphere::default_boxed();
+        let c = Cube::default_boxed();
+        let csg = Csg::new(None, CsgOp::Union, s.clone(), c.clone());
+        assert_eq!(csg.get_op(), CsgOp::Union);
+        assert_eq!(csg.get_left(), &s);
+        assert_eq!(csg.get_right(), &c);
+        assert!(csg.get_left().get_parent().is_some());
+        assert!(csg.get_right().get_parent().is_some());
+    }
+
+    #[test]
+    fn intersection_allowed() {
+        // (left_hit, in_left, in_right)
+        let cases = [
+            (true, true, true),
+            (true, true, false),
+            (true, false, true),
+            (true, false, false),
+            (false, true, true),
+            (false, true, false),
+            (false, false, true),
+            (false, false, false),
+        ];
+        let union = [false, true, false, true, false, false, true, true];
+        let intersection = [true, false, true, false, true, true, false, false];
+        let difference = [false, true, false, true, true, true, false, false];
+        for (c, u, i, d) in izip!(&cases, &union, &intersection, &difference) {
+            let (lhit, inl, inr) = *c;
+            assert_eq!(CsgOp::Union.intersection_allowed(lhit, inl, inr), *u);
+            assert_eq!(CsgOp::Intersection.intersection_allowed(lhit, inl, inr), *i);
+            assert_eq!(CsgOp::Difference.intersection_allowed(lhit, inl, inr), *d);
+        }
+    }
+
+    #[test]
+    fn filter_intersections() {
+        let ops = [CsgOp::Union, CsgOp::Intersection, CsgOp::Difference];
+        let exps = [(0, 3), (1, 2), (0, 1)];
+        for (op, (x0, x1)) in izip!(&ops, &exps) {
+            let csg = Csg::new(None, *op, Sphere::default_boxed(), Cube::default_boxed());
+            let xs: Intersections = vec![
+                Intersection::new(csg.get_left().clone(), 1.0),
+                Intersection::new(csg.get_right().clone(), 2.0),
+                Intersection::new(csg.get_left().clone(), 3.0),
+                Intersection::new(csg.get_right().clone(), 4.0),
+            ]
+            .into();
+            let result = csg.filter_intersections(xs.clone());
+            assert_eq!(result.len(), 2, "Failed for {:?}", op);
+            assert_eq!(result[0], xs[*x0]);
+            assert_eq!(result[1], xs[*x1]);
+        }
+    }
+
+    #[test]
+    fn filter_nested() {
+        let mut g = Group::default();
+        g.add_child(Sphere::default_boxed());
+        let csg = Csg::new(None, CsgOp::Difference, Box::new(g), Cube::default_boxed());
+        let left = csg.get_left().as_any().downcast_ref::<Group>().unwrap();
+        let sphere = left.get_children()[0].clone();
+        let xs: Intersections = vec![
+            Intersection::new(sphere.clone(), 1.0),
+            Intersection::new(csg.get_right().clone(), 2.0),
+            Intersection::new(sphere, 3.0),
+            Intersection::new(csg.get_right().clone(), 4.0),
+        ]
+        .into();
+        let result = csg.filter_intersections(xs.clone());
+        assert_eq!(result.len(), 2);
+        assert_eq!(result[0], xs[0]);
+        assert_eq!(result[1], xs[1]);
+    }
+
+    #[test]
+    fn ray_misses() {
+        let csg = Csg::new(
+            None,
+            CsgOp::Union,
+            Sphere::default_boxed(),
+            Cube::default_boxed(),
+        );
+        let r = Ray::new(&Vec4::point(0.0, 2.0, -5.0), &Vec4::VEC_Z_ONE);
+        assert!(csg.local_intersect(r).is_empty());
+    }
+
+    #[test]
+    fn ray_hits() {
+        let csg = Csg::new(
+            None,
+            CsgOp::Union,
+            Sphere::default_boxed(),
+            Sphere::new_boxed(Some(Mat4::translation(0.0, 0.0, 0.5)), None),
+        );
+        let r = Ray::new(&Vec4::point(0.0, 0.0, -5.0), &Vec4::VEC_Z_ONE);
+        let xs = csg.local_intersect(r);
+        assert_eq!(xs.len(), 2);
+        assert_eq!(xs[0].t, 4.0);
+        assert_eq!(&xs[0].object, csg.get_left());
+        assert_eq!(xs[1].t, 6.5);
+        assert_eq!(&xs[1].object, csg.get_right());
+    }
+
+    #[test]
+    fn hollow_sphere_normal() {
+        // sphere with a smaller one carved out, seen from the inside
+        let csg = Csg::new(
+            Some(Mat4::translation(0.0, 0.0, 10.0)),
+            CsgOp::Difference,
+            Sphere::new_boxed(Some(Mat4::scaling(2.0, 2.0, 2.0)), None),
+            Sphere::default_boxed(),
+        );
+        let r = Ray::new(&Vec4::point(0.0, 0.0, 0.0), &Vec4::VEC_Z_ONE);
+        let xs = csg.intersect(&r);
+        let ts: Vec<f64> = xs.get_inner_ref().iter().map(|i| i.t).collect();
+        assert_eq!(ts, vec![8.0, 9.0, 11.0, 12.0]);
+        let n = xs[1].object.normal_at(r.position(xs[1].t), &xs[1]);
+        assert_eq!(n, Vec4::vec(0.0, 0.0, -1.0));
+    }
+
+    #[test]
+    fn bounds() {
+        let csg = Csg::new(
+            None,
+            CsgOp::Difference,
+            Sphere::default_boxed(),
+            Sphere::new_boxed(Some(Mat4::translation(2.0, 3.0, 4.0)), None),
+        );
+        let b = csg.bounds();
+        assert_eq!(b.min, Vec4::point(-1.0, -1.0, -1.0));
+        assert_eq!(b.max, Vec4::point(3.0, 4.0, 5.0));
+    }
+}
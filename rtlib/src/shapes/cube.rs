@@ -1,14 +1,16 @@
+use crate::bounds::BoundingBox;
 use crate::intersection::{Intersection, Intersections};
 use crate::material::Material;
 use crate::math::matrix::Mat4;
 use crate::math::vec4::Vec4;
 use crate::math::EPSILON;
 use crate::ray::Ray;
-use crate::shapes::{BoxShape, Shape};
+use crate::shapes::{BoxShape, Parent, Shape};
 use crate::util::uid;
 
 use std::any::Any;
 use std::fmt::Debug;
+use std::sync::Arc;
 
 #[derive(Debug, Clone)]
 pub struct Cube {
@@ -16,6 +18,7 @@ pub struct Cube {
     pub transform: Mat4,
     pub inverse_transform: Mat4,
     pub material: Material,
+    parent: Option<Arc<Parent>>,
 }
 
 impl PartialEq for Cube {
@@ -44,6 +47,13 @@ impl Shape for Cube {
         &self.material
     }
 
+    fn get_parent(&self) -> Option<&Arc<Parent>> {
+        self.parent.as_ref()
+    }
+    fn set_parent(&mut self, parent: Option<Arc<Parent>>) {
+        self.parent = parent;
+    }
+
     fn transform(&mut self, m: &Mat4) {
         self.transform = m * self.transform;
     }
@@ -58,7 +68,11 @@ impl Shape for Cube {
         &self.inverse_transform
     }
 
-    fn local_normal_at(&self, local_point: Vec4) -> Vec4 {
+    fn bounds(&self) -> BoundingBox {
+        BoundingBox::new(Vec4::point(-1.0, -1.0, -1.0), Vec4::point(1.0, 1.0, 1.0))
+    }
+
+    fn local_normal_at(&self, local_point: Vec4, _hit: &Intersection) -> Vec4 {
         let abs_vec = local_point.abs();
         let max_c = abs_vec.x.max(abs_vec.y.max(abs_vec.z));
         if max_c == local_point.x.abs() {
@@ -99,6 +113,7 @@ impl Cube {
             transform,
             material: material.unwrap_or_default(),
             inverse_transform,
+            parent: None,
         }
     }
 
@@ -134,6 +149,7 @@ impl Default for Cube {
             transform: Mat4::default(),
             inverse_transform: Mat4::default(),
             material: Material::default(),
+            parent: None,
         }
     }
 }
@@ -223,6 +239,7 @@ mod tests {
     #[test]
     fn normal_at_surface() {
         let c = Cube::default();
+        let hit = Intersection::new(c.box_clone(), 0.0);
 
         let points = vec![
             Vec4::point(1.0, 0.5, -0.8),
@@ -247,10 +264,18 @@ mod tests {
         ];
 
         for (p, e) in izip!(&points, &exp) {
-            assert_eq!(c.normal_at(*p), *e, "Failed for {:?}", p);
+            assert_eq!(c.normal_at(*p, &hit), *e, "Failed for {:?}", p);
         }
     }
 
     #[test]
     fn normal_at_scaled_rotated() {}
+
+    #[test]
+    fn bounds() {
+        let c = Cube::default();
+        let b = c.bounds();
+        assert_eq!(b.min, Vec4::point(-1.0, -1.0, -1.0));
+        assert_eq!(b.max, Vec4::point(1.0, 1.0, 1.0));
+    }
 }
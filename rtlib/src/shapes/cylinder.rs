@@ -1,14 +1,16 @@
+use crate::bounds::BoundingBox;
 use crate::intersection::{Intersection, Intersections};
 use crate::material::Material;
 use crate::math::matrix::Mat4;
 use crate::math::vec4::Vec4;
 use crate::math::EPSILON;
 use crate::ray::Ray;
-use crate::shapes::{BoxShape, Shape};
+use crate::shapes::{BoxShape, Parent, Shape};
 use crate::util::uid;
 
 use std::any::Any;
 use std::fmt::Debug;
+use std::sync::Arc;
 
 #[derive(Debug, Clone)]
 pub struct Cylinder {
@@ -16,6 +18,7 @@ pub struct Cylinder {
     pub transform: Mat4,
     pub inverse_transform: Mat4,
     pub material: Material,
+    parent: Option<Arc<Parent>>,
     pub limit_y: (f64, f64),
     pub closed: bool,
 }
@@ -46,6 +49,13 @@ impl Shape for Cylinder {
         &self.material
     }
 
+    fn get_parent(&self) -> Option<&Arc<Parent>> {
+        self.parent.as_ref()
+    }
+    fn set_parent(&mut self, parent: Option<Arc<Parent>>) {
+        self.parent = parent;
+    }
+
     fn transform(&mut self, m: &Mat4) {
         self.transform = m * self.transform;
     }
@@ -60,7 +70,14 @@ impl Shape for Cylinder {
         &self.inverse_transform
     }
 
-    fn local_normal_at(&self, local_point: Vec4) -> Vec4 {
+    fn bounds(&self) -> BoundingBox {
+        BoundingBox::new(
+            Vec4::point(-1.0, self.limit_y.0, -1.0),
+            Vec4::point(1.0, self.limit_y.1, 1.0),
+        )
+    }
+
+    fn local_normal_at(&self, local_point: Vec4, _hit: &Intersection) -> Vec4 {
         let dist = local_point.x.powi(2) + local_point.z.powi(2);
         if dist < 1.0 && local_point.y >= self.limit_y.1 - EPSILON {
             Vec4::VEC_Y_ONE
@@ -122,6 +139,7 @@ impl Cylinder {
             transform,
             material: material.unwrap_or_default(),
             inverse_transform,
+            parent: None,
             limit_y,
             closed,
         }
@@ -166,6 +184,7 @@ impl Default for Cylinder {
             transform: Mat4::default(),
             inverse_transform: Mat4::default(),
             material: Material::default(),
+            parent: None,
             limit_y: (-f64::INFINITY, f64::INFINITY),
             closed: false,
         }
@@ -234,6 +253,7 @@ mod tests {
     #[test]
     fn infinite_normal_at() {
         let c = Cylinder::default();
+        let hit = Intersection::new(c.box_clone(), 0.0);
         let points = [
             Vec4::point(1.0, 0.0, 0.0),
             Vec4::point(0.0, 5.0, -1.0),
@@ -247,7 +267,7 @@ mod tests {
             -Vec4::VEC_X_ONE,
         ];
         for (p, exp) in izip!(&points, &exps) {
-            let n = c.local_normal_at(*p);
+            let n = c.local_normal_at(*p, &hit);
             assert_eq!(n, *exp);
         }
     }
@@ -344,4 +364,19 @@ mod tests {
             assert_eq!(xs.len(), *cnt);
         }
     }
+
+    #[test]
+    fn bounds() {
+        let c = Cylinder::default();
+        let b = c.bounds();
+        assert_eq!((b.min.x, b.min.z), (-1.0, -1.0));
+        assert_eq!((b.max.x, b.max.z), (1.0, 1.0));
+        assert!(b.min.y.is_infinite() && b.min.y < 0.0);
+        assert!(b.max.y.is_infinite() && b.max.y > 0.0);
+
+        let c = Cylinder::new(None, None, Some((-5.0, 3.0)), false);
+        let b = c.bounds();
+        assert_eq!(b.min, Vec4::point(-1.0, -5.0, -1.0));
+        assert_eq!(b.max, Vec4::point(1.0, 3.0, 1.0));
+    }
 }
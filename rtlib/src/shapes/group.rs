@@ -0,0 +1,561 @@
+use crate::bounds::BoundingBox;
+use crate::intersection::{Intersection, Intersections};
+use crate::material::Material;
+use crate::math::matrix::Mat4;
+use crate::math::vec4::Vec4;
+use crate::ray::Ray;
+use crate::shapes::{BoxShape, Parent, Shape};
+use crate::util::uid;
+
+use std::any::Any;
+use std::fmt::Debug;
+use std::sync::Arc;
+
+/// A *group* is a collection of shapes transformed as one.
+/// Children keep their own transform, the group's transform is applied on top of it.
+#[derive(Debug, Clone)]
+pub struct Group {
+    uid: usize,
+    pub transform: Mat4,
+    pub inverse_transform: Mat4,
+    pub material: Material,
+    parent: Option<Arc<Parent>>,
+    children: Vec<BoxShape>,
+    bounds: BoundingBox,
+}
+
+impl PartialEq for Group {
+    fn eq(&self, other: &Self) -> bool {
+        self.uid == other.uid
+            && self.transform == other.transform
+            && self.material == other.material
+    }
+}
+
+impl Shape for Group {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+    fn box_clone(&self) -> BoxShape {
+        Box::new((*self).clone())
+    }
+    fn box_eq(&self, other: &dyn Any) -> bool {
+        other.downcast_ref::<Self>().map_or(false, |a| self == a)
+    }
+
+    /// Sets the material of the group and all of its children
+    fn set_material(&mut self, material: Material) {
+        for child in self.children.iter_mut() {
+            child.set_material(material.clone());
+        }
+        self.material = material;
+    }
+    fn get_material(&self) -> &Material {
+        &self.material
+    }
+
+    fn get_parent(&self) -> Option<&Arc<Parent>> {
+        self.parent.as_ref()
+    }
+    fn set_parent(&mut self, parent: Option<Arc<Parent>>) {
+        self.parent = parent;
+        self.update_children();
+    }
+
+    fn transform(&mut self, m: &Mat4) {
+        self.set_transform(m * self.transform);
+    }
+    fn set_transform(&mut self, transform: Mat4) {
+        self.transform = transform;
+        self.inverse_transform = self.transform.inverse().unwrap();
+        self.update_children();
+    }
+    fn transformation(&self) -> &Mat4 {
+        &self.transform
+    }
+    fn inverse_transformation(&self) -> &Mat4 {
+        &self.inverse_transform
+    }
+
+    fn bounds(&self) -> BoundingBox {
+        self.bounds
+    }
+
+    fn divide(&mut self, threshold: usize) {
+        if threshold <= self.children.len() {
+            let (left, right) = self.partition_children();
+            if !left.is_empty() {
+                self.add_subgroup(left);
+            }
+            if !right.is_empty() {
+                self.add_subgroup(right);
+            }
+        }
+        for child in self.children.iter_mut() {
+            child.divide(threshold);
+        }
+    }
+
+    fn intersects_within(&self, ray: &Ray, max_t: f64) -> bool {
+        // transformations keep `t`, so `max_t` holds in the local space too
+        let local_ray = ray.transform(&self.inverse_transform);
+        self.bounds.intersects(&local_ray)
+            && self
+                .children
+                .iter()
+                .any(|c| c.intersects_within(&local_ray, max_t))
+    }
+
+    fn blocks_light_within(&self, ray: &Ray, max_t: f64) -> bool {
+        let local_ray = ray.transform(&self.inverse_transform);
+        self.bounds.intersects(&local_ray)
+            && self
+                .children
+                .iter()
+                .any(|c| c.blocks_light_within(&local_ray, max_t))
+    }
+
+    fn includes(&self, other: &dyn Shape) -> bool {
+        self.children.iter().any(|c| c.includes(other))
+    }
+
+    fn local_normal_at(&self, _local_point: Vec4, _hit: &Intersection) -> Vec4 {
+        unreachable!("Group has no surface of its own, normals come from its children")
+    }
+
+    fn local_intersect(&self, local_ray: Ray) -> Intersections {
+        let mut ret = Intersections::new();
+        if !self.bounds.intersects(&local_ray) {
+            return ret;
+        }
+        for child in self.children.iter() {
+            let mut xs = child.intersect(&local_ray);
+            ret.append(&mut xs);
+        }
+        ret.sort();
+        ret
+    }
+}
+
+impl Group {
+    pub fn new(transform: Option<Mat4>) -> Self {
+        let transform = transform.unwrap_or_default();
+        let inverse_transform = transform.inverse().unwrap();
+        Self {
+            uid: uid::fetch_uid(),
+            transform,
+            material: Material::default(),
+            inverse_transform,
+            parent: None,
+            children: Vec::new(),
+            bounds: BoundingBox::default(),
+        }
+    }
+
+    pub fn new_boxed(transform: Option<Mat4>) -> BoxShape {
+        Box::new(Self::new(transform))
+    }
+
+    pub fn default_boxed() -> BoxShape {
+        Box::new(Self::default())
+    }
+
+    pub fn add_child(&mut self, mut child: BoxShape) {
+        child.set_parent(Some(self.as_parent()));
+        self.bounds.merge(&child.parent_space_bounds());
+        self.children.push(child);
+    }
+
+    pub fn get_children(&self) -> &Vec<BoxShape> {
+        &self.children
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.children.is_empty()
+    }
+
+    /// Takes out the children that fit entirely into either half
+    /// of the group's bounds. Children with infinite bounds always stay.
+    fn partition_children(&mut self) -> (Vec<BoxShape>, Vec<BoxShape>) {
+        let mut finite_bounds = BoundingBox::default();
+        for child in self.children.iter() {
+            let b = child.parent_space_bounds();
+            if !b.is_infinite() {
+                finite_bounds.merge(&b);
+            }
+        }
+        let (left_bounds, right_bounds) = finite_bounds.split();
+
+        let count = self.children.len();
+        let mut left = Vec::new();
+        let mut right = Vec::new();
+        let mut remaining = Vec::new();
+        for child in self.children.drain(..) {
+            let b = child.parent_space_bounds();
+            if b.is_infinite() {
+                remaining.push(child);
+            } else if left_bounds.contains_box(&b) {
+                left.push(child);
+            } else if right_bounds.contains_box(&b) {
+                right.push(child);
+            } else {
+                remaining.push(child);
+            }
+        }
+
+        // Degenerate bounds can't be split any further,
+        // moving everything into a single subgroup would never end.
+        if left.len() == count || right.len() == count {
+            remaining.append(&mut left);
+            remaining.append(&mut right);
+        }
+        self.children = remaining;
+        (left, right)
+    }
+
+    fn add_subgroup(&mut self, children: Vec<BoxShape>) {
+        let mut subgroup = Group::default();
+        for child in children.into_iter() {
+            subgroup.add_child(child);
+        }
+        self.add_child(Box::new(subgroup));
+    }
+
+    fn as_parent(&self) -> Arc<Parent> {
+        Arc::new(Parent {
+            inverse_transform: self.inverse_transform,
+            parent: self.parent.clone(),
+        })
+    }
+
+    /// Children hold a copy of the parent chain,
+    /// so it has to be refreshed whenever the group moves.
+    fn update_children(&mut self) {
+        let parent = self.as_parent();
+        for child in self.children.iter_mut() {
+            child.set_parent(Some(parent.clone()));
+        }
+    }
+}
+
+impl Default for Group {
+    fn default() -> Self {
+        Self {
+            uid: uid::fetch_uid(),
+            transform: Mat4::default(),
+            inverse_transform: Mat4::default(),
+            material: Material::default(),
+            parent: None,
+            children: Vec::new(),
+            bounds: BoundingBox::default(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::shapes::{Cylinder, SmoothTriangle, Sphere};
+    use std::f64::consts::PI;
+
+    #[test]
+    fn basic() {
+        let g = Group::default();
+        assert_eq!(g.transform, Mat4::IDENTITY);
+        assert!(g.is_empty());
+    }
+
+    #[test]
+    fn add_child() {
+        let mut g = Group::default();
+        let s = Sphere::default_boxed();
+        g.add_child(s.clone());
+        assert_eq!(g.get_children().len(), 1);
+        assert_eq!(&g.get_children()[0], &s);
+        assert!(g.get_children()[0].get_parent().is_some());
+    }
+
+    #[test]
+    fn intersect_empty() {
+        let g = Group::default();
+        let r = Ray::new(&Vec4::POINT_ZERO, &Vec4::VEC_Z_ONE);
+        assert!(g.local_intersect(r).is_empty());
+    }
+
+    #[test]
+    fn intersect_children() {
+        let mut g = Group::default();
+        let s1 = Sphere::default_boxed();
+        let s2 = Sphere::new_boxed(Some(Mat4::translation(0.0, 0.0, -3.0)), None);
+        let s3 = Sphere::new_boxed(Some(Mat4::translation(5.0, 0.0, 0.0)), None);
+        g.add_child(s1.clone());
+        g.add_child(s2.clone());
+        g.add_child(s3);
+
+        let r = Ray::new(&Vec4::point(0.0, 0.0, -5.0), &Vec4::VEC_Z_ONE);
+        let xs = g.local_intersect(r);
+        assert_eq!(xs.len(), 4);
+        assert_eq!(&xs[0].object, &s2);
+        assert_eq!(&xs[1].object, &s2);
+        assert_eq!(&xs[2].object, &s1);
+        assert_eq!(&xs[3].object, &s1);
+    }
+
+    #[test]
+    fn intersect_transformed() {
+        let mut g = Group::new(Some(Mat4::scaling(2.0, 2.0, 2.0)));
+        g.add_child(Sphere::new_boxed(
+            Some(Mat4::translation(5.0, 0.0, 0.0)),
+            None,
+        ));
+        let r = Ray::new(&Vec4::point(10.0, 0.0, -10.0), &Vec4::VEC_Z_ONE);
+        assert_eq!(g.intersect(&r).len(), 2);
+    }
+
+    fn nested_sphere(scaling: Mat4) -> Group {
+        let mut g2 = Group::new(Some(scaling));
+        g2.add_child(Sphere::new_boxed(
+            Some(Mat4::translation(5.0, 0.0, 0.0)),
+            None,
+        ));
+        let mut g1 = Group::new(None);
+        g1.add_child(Box::new(g2));
+        g1.set_transform(Mat4::rotation_y(PI / 2.0));
+        g1
+    }
+
+    fn nested_child(g1: &Group) -> &BoxShape {
+        let g2 = g1.get_children()[0]
+            .as_any()
+            .downcast_ref::<Group>()
+            .unwrap();
+        &g2.get_children()[0]
+    }
+
+    #[test]
+    fn world_to_object() {
+        let g1 = nested_sphere(Mat4::scaling(2.0, 2.0, 2.0));
+        let s = nested_child(&g1);
+        let p = s.world_to_object(Vec4::point(-2.0, 0.0, -10.0));
+        assert_eq!(p, Vec4::point(0.0, 0.0, -1.0));
+    }
+
+    #[test]
+    fn normal_to_world() {
+        let g1 = nested_sphere(Mat4::scaling(1.0, 2.0, 3.0));
+        let s = nested_child(&g1);
+        let v = 3f64.sqrt() / 3.0;
+        let n = s.normal_to_world(Vec4::vec(v, v, v));
+        assert_eq!(n, Vec4::vec(0.28571, 0.42857, -0.85714));
+    }
+
+    #[test]
+    fn normal_at_child() {
+        let g1 = nested_sphere(Mat4::scaling(1.0, 2.0, 3.0));
+        let s = nested_child(&g1);
+        let hit = Intersection::new(s.clone(), 0.0);
+        let n = s.normal_at(Vec4::point(1.7321, 1.1547, -5.5774), &hit);
+        assert_eq!(n, Vec4::vec(0.2857, 0.42854, -0.85716));
+    }
+
+    #[test]
+    fn transformed_after_adding() {
+        let mut g = Group::default();
+        g.add_child(Sphere::default_boxed());
+        g.set_transform(Mat4::translation(0.0, 0.0, 5.0));
+        let s = &g.get_children()[0];
+        assert_eq!(
+            s.world_to_object(Vec4::point(0.0, 0.0, 5.0)),
+            Vec4::POINT_ZERO
+        );
+        let r = Ray::new(&Vec4::POINT_ZERO, &Vec4::VEC_Z_ONE);
+        let xs = g.intersect(&r);
+        assert_eq!(xs.len(), 2);
+        assert_eq!(xs[0].t, 4.0);
+        let n = xs[0].object.normal_at(r.position(4.0), &xs[0]);
+        assert_eq!(n, -Vec4::VEC_Z_ONE);
+    }
+
+    #[test]
+    fn bounds() {
+        let mut g = Group::default();
+        g.add_child(Sphere::new_boxed(
+            Some(Mat4::translation(2.0, 5.0, -3.0) * Mat4::scaling(2.0, 2.0, 2.0)),
+            None,
+        ));
+        g.add_child(Cylinder::new_boxed(
+            Some(Mat4::translation(-4.0, -1.0, 4.0) * Mat4::scaling(0.5, 1.0, 0.5)),
+            None,
+            Some((-2.0, 2.0)),
+            false,
+        ));
+        let b = g.bounds();
+        assert_eq!(b.min, Vec4::point(-4.5, -3.0, -5.0));
+        assert_eq!(b.max, Vec4::point(4.0, 7.0, 4.5));
+    }
+
+    #[test]
+    fn intersect_misses_bounds() {
+        let mut g = Group::default();
+        g.add_child(Sphere::new_boxed(
+            Some(Mat4::translation(0.0, 0.0, 5.0)),
+            None,
+        ));
+        let r = Ray::new(&Vec4::point(0.0, 3.0, -5.0), &Vec4::VEC_Z_ONE);
+        assert!(g.intersect(&r).is_empty());
+        let r = Ray::new(&Vec4::point(0.0, 0.0, -5.0), &Vec4::VEC_Z_ONE);
+        assert_eq!(g.intersect(&r).len(), 2);
+    }
+
+    fn as_group(shape: &BoxShape) -> &Group {
+        shape.as_any().downcast_ref::<Group>().unwrap()
+    }
+
+    #[test]
+    fn partition_children() {
+        let s1 = Sphere::new_boxed(Some(Mat4::translation(-2.0, 0.0, 0.0)), None);
+        let s2 = Sphere::new_boxed(Some(Mat4::translation(2.0, 0.0, 0.0)), None);
+        let s3 = Sphere::default_boxed();
+        let mut g = Group::default();
+        g.add_child(s1.clone());
+        g.add_child(s2.clone());
+        g.add_child(s3.clone());
+        let (left, right) = g.partition_children();
+        assert_eq!(g.get_children(), &vec![s3]);
+        assert_eq!(left, vec![s1]);
+        assert_eq!(right, vec![s2]);
+    }
+
+    #[test]
+    fn divide() {
+        let s1 = Sphere::new_boxed(Some(Mat4::translation(-2.0, -2.0, 0.0)), None);
+        let s2 = Sphere::new_boxed(Some(Mat4::translation(-2.0, 2.0, 0.0)), None);
+        let s3 = Sphere::new_boxed(Some(Mat4::scaling(4.0, 4.0, 4.0)), None);
+        let mut g = Group::default();
+        g.add_child(s1.clone());
+        g.add_child(s2.clone());
+        g.add_child(s3.clone());
+        g.divide(1);
+
+        let children = g.get_children();
+        assert_eq!(children.len(), 2);
+        assert_eq!(&children[0], &s3);
+        let sub = as_group(&children[1]);
+        assert_eq!(sub.get_children().len(), 2);
+        assert_eq!(as_group(&sub.get_children()[0]).get_children(), &vec![s1]);
+        assert_eq!(as_group(&sub.get_children()[1]).get_children(), &vec![s2]);
+    }
+
+    #[test]
+    fn divide_below_threshold() {
+        let s1 = Sphere::new_boxed(Some(Mat4::translation(-2.0, 0.0, 0.0)), None);
+        let s2 = Sphere::new_boxed(Some(Mat4::translation(2.0, 1.0, 0.0)), None);
+        let s3 = Sphere::new_boxed(Some(Mat4::translation(2.0, -1.0, 0.0)), None);
+        let mut sub = Group::default();
+        sub.add_child(s1.clone());
+        sub.add_child(s2.clone());
+        sub.add_child(s3.clone());
+        let s4 = Sphere::default_boxed();
+        let mut g = Group::default();
+        g.add_child(Box::new(sub));
+        g.add_child(s4.clone());
+        g.divide(3);
+
+        let children = g.get_children();
+        assert_eq!(children.len(), 2);
+        assert_eq!(&children[1], &s4);
+        let sub = as_group(&children[0]);
+        assert_eq!(sub.get_children().len(), 2);
+        assert_eq!(as_group(&sub.get_children()[0]).get_children(), &vec![s1]);
+        assert_eq!(
+            as_group(&sub.get_children()[1]).get_children(),
+            &vec![s2, s3]
+        );
+    }
+
+    #[test]
+    fn divide_degenerate() {
+        let mut g = Group::default();
+        let p = Vec4::point(1.0, 2.0, 3.0);
+        for _ in 0..4 {
+            g.add_child(SmoothTriangle::new_boxed(
+                None,
+                None,
+                [p, p, p],
+                [Vec4::VEC_Y_ONE; 3],
+            ));
+        }
+        g.divide(1);
+        assert_eq!(g.get_children().len(), 4);
+    }
+
+    #[test]
+    fn divided_intersections_unchanged() {
+        let mut g = Group::new(Some(Mat4::rotation_y(PI / 3.0)));
+        for i in 0..8 {
+            let x = i as f64 * 2.5 - 10.0;
+            g.add_child(Sphere::new_boxed(
+                Some(Mat4::translation(x, 0.0, 0.0)),
+                None,
+            ));
+        }
+        let mut divided = g.clone();
+        divided.divide(2);
+        let r = Ray::new(
+            &Vec4::point(-10.0, 0.0, -10.0),
+            &Vec4::vec(1.0, 0.0, 1.0).normalize(),
+        );
+        let xs = g.intersect(&r);
+        let divided_xs = divided.intersect(&r);
+        assert_eq!(xs.len(), divided_xs.len());
+        for i in 0..xs.len() {
+            assert_eq!(xs[i].t, divided_xs[i].t);
+            let p = r.position(xs[i].t);
+            assert_eq!(
+                xs[i].object.normal_at(p, &xs[i]),
+                divided_xs[i].object.normal_at(p, &divided_xs[i])
+            );
+        }
+    }
+
+    #[test]
+    fn includes() {
+        let s = Sphere::default_boxed();
+        let mut sub = Group::default();
+        sub.add_child(s.clone());
+        let mut g = Group::default();
+        g.add_child(Box::new(sub));
+        assert!(g.includes(s.as_ref()));
+        assert!(!g.includes(Sphere::default_boxed().as_ref()));
+    }
+
+    #[test]
+    fn intersects_within() {
+        let mut g = Group::new(Some(Mat4::scaling(2.0, 2.0, 2.0)));
+        g.add_child(Sphere::new_boxed(
+            Some(Mat4::translation(5.0, 0.0, 0.0)),
+            None,
+        ));
+        let r = Ray::new(&Vec4::point(10.0, 0.0, -10.0), &Vec4::VEC_Z_ONE);
+        // sphere spans t 8 to 12 in world space
+        assert!(g.intersects_within(&r, 9.0));
+        assert!(!g.intersects_within(&r, 8.0));
+        let r = Ray::new(&Vec4::point(10.0, 0.0, 20.0), &Vec4::VEC_Z_ONE);
+        assert!(!g.intersects_within(&r, f64::INFINITY));
+    }
+
+    #[test]
+    fn blocks_light_within() {
+        let mut g = Group::default();
+        g.add_child(Sphere::new_boxed(None, Some(Material::GLASS)));
+        g.add_child(Sphere::new_boxed(
+            Some(Mat4::translation(0.0, 0.0, 5.0)),
+            None,
+        ));
+        let r = Ray::new(&Vec4::point(0.0, 0.0, -5.0), &Vec4::VEC_Z_ONE);
+        // only glass in the way
+        assert!(g.intersects_within(&r, 6.0));
+        assert!(!g.blocks_light_within(&r, 6.0));
+        assert!(g.blocks_light_within(&r, 10.0));
+    }
+}
@@ -0,0 +1,416 @@
+use crate::bounds::BoundingBox;
+use crate::intersection::{Intersection, Intersections};
+use crate::material::Material;
+use crate::math::matrix::{Decomposition, Mat4};
+use crate::math::vec4::Vec4;
+use crate::ray::Ray;
+use crate::shapes::{BoxShape, Parent, Shape};
+use crate::util::uid;
+
+use std::any::Any;
+use std::fmt::Debug;
+use std::sync::Arc;
+
+/// Shape in motion, its transform goes from `start` at time `0.0`
+/// to `end` at time `1.0` and stays put outside of that.
+/// Rays are intersected with the shape where it is at the ray's time,
+/// so a camera shutter spanning the motion blurs it.
+///
+/// In between the keyframes the shape moves and scales linearly
+/// and turns at constant speed the shorter way around.
+#[derive(Debug, Clone)]
+pub struct Moving {
+    uid: usize,
+    pub start: Mat4,
+    pub end: Mat4,
+    pub inverse_start: Mat4,
+    pub material: Material,
+    start_parts: Decomposition,
+    end_parts: Decomposition,
+    parent: Option<Arc<Parent>>,
+    /// Parent given to the child, places it as at time `0.0`
+    placement: Arc<Parent>,
+    child: BoxShape,
+}
+
+impl PartialEq for Moving {
+    fn eq(&self, other: &Self) -> bool {
+        self.uid == other.uid
+            && self.start == other.start
+            && self.end == other.end
+            && self.material == other.material
+    }
+}
+
+impl Shape for Moving {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+    fn box_clone(&self) -> BoxShape {
+        Box::new((*self).clone())
+    }
+    fn box_eq(&self, other: &dyn Any) -> bool {
+        other.downcast_ref::<Self>().map_or(false, |a| self == a)
+    }
+
+    /// Sets the material of the shape that moves as well
+    fn set_material(&mut self, material: Material) {
+        self.child.set_material(material.clone());
+        self.material = material;
+    }
+    fn get_material(&self) -> &Material {
+        &self.material
+    }
+
+    fn get_parent(&self) -> Option<&Arc<Parent>> {
+        self.parent.as_ref()
+    }
+    fn set_parent(&mut self, parent: Option<Arc<Parent>>) {
+        self.parent = parent;
+        self.update_child();
+    }
+
+    /// Applies `m` to both keyframes
+    fn transform(&mut self, m: &Mat4) {
+        self.set_keyframes(m * self.start, m * self.end);
+    }
+    /// Stops the motion, both keyframes become `transform`
+    fn set_transform(&mut self, transform: Mat4) {
+        self.set_keyframes(transform, transform);
+    }
+    /// Transform at time `0.0`
+    fn transformation(&self) -> &Mat4 {
+        &self.start
+    }
+    fn inverse_transformation(&self) -> &Mat4 {
+        &self.inverse_start
+    }
+
+    fn bounds(&self) -> BoundingBox {
+        self.child.parent_space_bounds()
+    }
+
+    /// Covers the whole motion. Without turning, every point moves along
+    /// a straight line between its positions at the two keyframes.
+    /// Turning shapes sweep arcs, they are covered by the reach of
+    /// the shape around the moving center of rotation instead.
+    fn parent_space_bounds(&self) -> BoundingBox {
+        let bounds = self.bounds();
+        let (start, end) = (&self.start_parts, &self.end_parts);
+        if bounds.is_empty() || start.rotation.dot(&end.rotation).abs() >= 1.0 - 1e-12 {
+            let mut ret = bounds.transform(&self.start);
+            ret.merge(&bounds.transform(&self.end));
+            return ret;
+        }
+        // stretch blends linearly, so its reach never exceeds the larger
+        // of the two keyframes, and rotating keeps it
+        let reach = [start.stretch, end.stretch]
+            .iter()
+            .map(|stretch| {
+                let b = bounds.transform(stretch);
+                let x = b.min.x.abs().max(b.max.x.abs());
+                let y = b.min.y.abs().max(b.max.y.abs());
+                let z = b.min.z.abs().max(b.max.z.abs());
+                (x * x + y * y + z * z).sqrt()
+            })
+            .fold(0.0, f64::max);
+        let mut ret = BoundingBox::EMPTY;
+        for t in [start.translation, end.translation].iter() {
+            ret.add_point(&Vec4::point(t.x - reach, t.y - reach, t.z - reach));
+            ret.add_point(&Vec4::point(t.x + reach, t.y + reach, t.z + reach));
+        }
+        ret
+    }
+
+    fn divide(&mut self, threshold: usize) {
+        self.child.divide(threshold);
+    }
+
+    fn includes(&self, other: &dyn Shape) -> bool {
+        self.child.includes(other)
+    }
+
+    /// Intersects the child where it is at the ray's time.
+    /// The hit shapes are placed at that time as well, so normals
+    /// and patterns come out right.
+    fn intersect(&self, world_ray: &Ray) -> Intersections {
+        if !self.parent_space_bounds().intersects(world_ray) {
+            return Intersections::new();
+        }
+        if world_ray.time <= 0.0 || self.start == self.end {
+            return self
+                .child
+                .intersect(&world_ray.transform(&self.inverse_start));
+        }
+        let inverse = match self.transform_at(world_ray.time).inverse() {
+            Ok(inverse) => inverse,
+            Err(_) => return Intersections::new(),
+        };
+        let mut xs = self.child.intersect(&world_ray.transform(&inverse));
+        let placed = Arc::new(Parent {
+            inverse_transform: inverse,
+            parent: self.parent.clone(),
+        });
+        for i in xs.iter_mut() {
+            let parent = i
+                .object
+                .get_parent()
+                .map(|parent| replace_parent(parent, &self.placement, &placed));
+            i.object.set_parent(parent);
+        }
+        xs
+    }
+
+    fn local_normal_at(&self, _local_point: Vec4, _hit: &Intersection) -> Vec4 {
+        unreachable!("Moving has no surface of its own, normals come from its child")
+    }
+
+    /// Intersection at time `0.0`
+    fn local_intersect(&self, local_ray: Ray) -> Intersections {
+        self.child.intersect(&local_ray)
+    }
+}
+
+impl Moving {
+    pub fn new(start: Mat4, end: Mat4, child: BoxShape) -> Self {
+        let inverse_start = start.inverse().unwrap();
+        let placement = Arc::new(Parent {
+            inverse_transform: inverse_start,
+            parent: None,
+        });
+        let mut ret = Self {
+            uid: uid::fetch_uid(),
+            start,
+            end,
+            inverse_start,
+            material: Material::default(),
+            start_parts: start.decompose().unwrap(),
+            end_parts: end.decompose().unwrap(),
+            parent: None,
+            placement,
+            child,
+        };
+        ret.update_child();
+        ret
+    }
+
+    pub fn new_boxed(start: Mat4, end: Mat4, child: BoxShape) -> BoxShape {
+        Box::new(Self::new(start, end, child))
+    }
+
+    pub fn get_child(&self) -> &BoxShape {
+        &self.child
+    }
+
+    pub fn set_keyframes(&mut self, start: Mat4, end: Mat4) {
+        self.start = start;
+        self.end = end;
+        self.inverse_start = start.inverse().unwrap();
+        self.start_parts = start.decompose().unwrap();
+        self.end_parts = end.decompose().unwrap();
+        self.update_child();
+    }
+
+    /// Transform at `time`, clamped to the keyframes
+    pub fn transform_at(&self, time: f64) -> Mat4 {
+        if time <= 0.0 {
+            self.start
+        } else if time >= 1.0 {
+            self.end
+        } else {
+            self.start_parts
+                .interpolate(&self.end_parts, time)
+                .to_matrix()
+        }
+    }
+
+    /// The child is placed as at time `0.0` until a ray says otherwise
+    fn update_child(&mut self) {
+        self.placement = Arc::new(Parent {
+            inverse_transform: self.inverse_start,
+            parent: self.parent.clone(),
+        });
+        self.child.set_parent(Some(self.placement.clone()));
+    }
+}
+
+/// Copy of the `parent` chain with `from` swapped for `to`
+fn replace_parent(parent: &Arc<Parent>, from: &Arc<Parent>, to: &Arc<Parent>) -> Arc<Parent> {
+    if Arc::ptr_eq(parent, from) {
+        return to.clone();
+    }
+    match &parent.parent {
+        Some(up) => Arc::new(Parent {
+            inverse_transform: parent.inverse_transform,
+            parent: Some(replace_parent(up, from, to)),
+        }),
+        None => parent.clone(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::math::EPSILON;
+    use crate::shapes::{Csg, CsgOp, Cube, Group, Sphere};
+    use itertools::izip;
+    use std::f64::consts::PI;
+
+    fn moving_sphere() -> Moving {
+        Moving::new(
+            Mat4::IDENTITY,
+            Mat4::translation(2.0, 0.0, 0.0),
+            Sphere::default_boxed(),
+        )
+    }
+
+    #[test]
+    fn transform_at() {
+        let m = moving_sphere();
+        let times = [-1.0, 0.0, 0.5, 1.0, 2.0];
+        let exps = [0.0, 0.0, 1.0, 2.0, 2.0];
+        for (time, x) in izip!(&times, &exps) {
+            assert_eq!(m.transform_at(*time), Mat4::translation(*x, 0.0, 0.0));
+        }
+    }
+
+    #[test]
+    fn intersect_at_time() {
+        let m = moving_sphere();
+        let times = [0.0, 0.5, 1.0];
+        let exps = [0, 2, 2];
+        for (time, count) in izip!(&times, &exps) {
+            let r = Ray::new_with_time(&Vec4::point(2.0, 0.0, -5.0), &Vec4::VEC_Z_ONE, *time);
+            let xs = m.intersect(&r);
+            assert_eq!(xs.len(), *count, "Failed for {}", time);
+        }
+        let r = Ray::new_with_time(&Vec4::point(2.0, 0.0, -5.0), &Vec4::VEC_Z_ONE, 1.0);
+        let xs = m.intersect(&r);
+        assert_eq!(xs[0].t, 4.0);
+        assert_eq!(xs[1].t, 6.0);
+    }
+
+    #[test]
+    fn normal_at_time() {
+        let mut g = Group::default();
+        g.add_child(Box::new(moving_sphere()));
+        let r = Ray::new_with_time(&Vec4::point(2.5, 0.0, -5.0), &Vec4::VEC_Z_ONE, 1.0);
+        let xs = g.intersect(&r);
+        assert_eq!(xs.len(), 2);
+        let n = xs[0].object.normal_at(r.position(xs[0].t), &xs[0]);
+        assert_eq!(n, Vec4::vec(0.5, 0.0, -(0.75f64).sqrt()));
+    }
+
+    #[test]
+    fn rotation_keeps_extent() {
+        // a 4x2x2 box turning half way around
+        let m = Moving::new(
+            Mat4::IDENTITY,
+            Mat4::rotation_y(PI),
+            Box::new(Cube::new(Some(Mat4::scaling(2.0, 1.0, 1.0)), None)),
+        );
+        let origins = [Vec4::point(0.0, 0.0, -5.0), Vec4::point(-5.0, 0.0, 0.0)];
+        let directions = [Vec4::VEC_Z_ONE, Vec4::VEC_X_ONE];
+        let times = [0.0, 0.5, 1.0];
+        let exps = [[4.0, 3.0], [3.0, 4.0], [4.0, 3.0]];
+        for (time, exp) in izip!(&times, &exps) {
+            for (origin, direction, t) in izip!(&origins, &directions, exp) {
+                let r = Ray::new_with_time(origin, direction, *time);
+                let xs = m.intersect(&r);
+                assert_eq!(xs.len(), 2, "Failed for {}", time);
+                assert!((xs[0].t - t).abs() < EPSILON, "Failed for {}", time);
+            }
+        }
+        // turned 90 degrees, the long side faces the ray
+        let r = Ray::new_with_time(&origins[0], &directions[0], 0.5);
+        let xs = m.intersect(&r);
+        let n = xs[0].object.normal_at(r.position(xs[0].t), &xs[0]);
+        assert_eq!(n, Vec4::vec(0.0, 0.0, -1.0));
+    }
+
+    #[test]
+    fn rotation_bounds() {
+        let m = Moving::new(
+            Mat4::IDENTITY,
+            Mat4::translation(1.0, 0.0, 0.0) * Mat4::rotation_y(PI),
+            Box::new(Cube::new(Some(Mat4::scaling(2.0, 1.0, 1.0)), None)),
+        );
+        let bounds = m.parent_space_bounds();
+        let child = m.bounds();
+        for i in 0..=20 {
+            let time = i as f64 / 20.0;
+            let b = child.transform(&m.transform_at(time));
+            assert!(bounds.contains_box(&b), "Failed for {}", time);
+        }
+
+        // off the axes, where neither keyframe reaches
+        let mut g = Group::default();
+        g.add_child(Box::new(m));
+        let r = Ray::new_with_time(&Vec4::point(-5.0, 0.0, -1.5), &Vec4::VEC_X_ONE, 0.5);
+        let xs = g.intersect(&r);
+        assert_eq!(xs.len(), 2);
+        assert!((xs[0].t - 4.5).abs() < EPSILON);
+    }
+
+    #[test]
+    fn shared_child() {
+        // hits at other times don't move the child itself
+        let m = moving_sphere();
+        let r = Ray::new_with_time(&Vec4::point(2.0, 0.0, -5.0), &Vec4::VEC_Z_ONE, 1.0);
+        assert_eq!(m.intersect(&r).len(), 2);
+        let r = Ray::new(&Vec4::point(0.0, 0.0, -5.0), &Vec4::VEC_Z_ONE);
+        let xs = m.intersect(&r);
+        let n = xs[0].object.normal_at(r.position(xs[0].t), &xs[0]);
+        assert_eq!(n, Vec4::vec(0.0, 0.0, -1.0));
+    }
+
+    #[test]
+    fn bounds() {
+        let m = moving_sphere();
+        let b = m.parent_space_bounds();
+        assert_eq!(b.min, Vec4::point(-1.0, -1.0, -1.0));
+        assert_eq!(b.max, Vec4::point(3.0, 1.0, 1.0));
+
+        let mut g = Group::default();
+        g.add_child(Box::new(m));
+        assert_eq!(g.bounds().max, Vec4::point(3.0, 1.0, 1.0));
+    }
+
+    #[test]
+    fn set_transform() {
+        let mut m = moving_sphere();
+        m.set_transform(Mat4::translation(0.0, 1.0, 0.0));
+        assert_eq!(m.transform_at(0.5), Mat4::translation(0.0, 1.0, 0.0));
+        m.set_keyframes(Mat4::IDENTITY, Mat4::scaling(3.0, 3.0, 3.0));
+        assert_eq!(m.transform_at(0.5), Mat4::scaling(2.0, 2.0, 2.0));
+        m.transform(&Mat4::translation(1.0, 0.0, 0.0));
+        assert_eq!(m.start, Mat4::translation(1.0, 0.0, 0.0));
+        assert_eq!(
+            m.end,
+            Mat4::translation(1.0, 0.0, 0.0) * Mat4::scaling(3.0, 3.0, 3.0)
+        );
+    }
+
+    #[test]
+    fn in_csg() {
+        // moving sphere carving into a static one
+        let csg = Csg::new(
+            None,
+            CsgOp::Difference,
+            Sphere::new_boxed(Some(Mat4::scaling(2.0, 2.0, 2.0)), None),
+            Moving::new_boxed(
+                Mat4::translation(0.0, 0.0, -5.0),
+                Mat4::translation(0.0, 0.0, -1.5),
+                Sphere::default_boxed(),
+            ),
+        );
+        let r = Ray::new_with_time(&Vec4::point(0.0, 0.0, -5.0), &Vec4::VEC_Z_ONE, 0.0);
+        let xs = csg.intersect(&r);
+        assert_eq!(xs[0].t, 3.0);
+        let r = Ray::new_with_time(&Vec4::point(0.0, 0.0, -5.0), &Vec4::VEC_Z_ONE, 1.0);
+        let xs = csg.intersect(&r);
+        assert_eq!(xs.len(), 2);
+        assert_eq!(xs[0].t, 4.5);
+        assert!(csg.get_right().includes(xs[0].object.as_ref()));
+    }
+}
@@ -1,14 +1,16 @@
+use crate::bounds::BoundingBox;
 use crate::intersection::{Intersection, Intersections};
 use crate::material::Material;
 use crate::math::matrix::Mat4;
 use crate::math::vec4::Vec4;
 use crate::math::EPSILON;
 use crate::ray::Ray;
-use crate::shapes::{BoxShape, Shape};
+use crate::shapes::{BoxShape, Parent, Shape};
 use crate::util::uid;
 
 use std::any::Any;
 use std::fmt::Debug;
+use std::sync::Arc;
 
 /// A *plane* is a perfectly flat surface tha extends infinitely in two dimensions.
 /// The default plane is considered to be xz-plane.
@@ -18,6 +20,7 @@ pub struct Plane {
     pub transform: Mat4,
     pub inverse_transform: Mat4,
     pub material: Material,
+    parent: Option<Arc<Parent>>,
 }
 
 impl PartialEq for Plane {
@@ -46,6 +49,13 @@ impl Shape for Plane {
         &self.material
     }
 
+    fn get_parent(&self) -> Option<&Arc<Parent>> {
+        self.parent.as_ref()
+    }
+    fn set_parent(&mut self, parent: Option<Arc<Parent>>) {
+        self.parent = parent;
+    }
+
     fn transform(&mut self, m: &Mat4) {
         self.transform = m * self.transform;
     }
@@ -60,7 +70,14 @@ impl Shape for Plane {
         &self.inverse_transform
     }
 
-    fn local_normal_at(&self, _local_point: Vec4) -> Vec4 {
+    fn bounds(&self) -> BoundingBox {
+        BoundingBox::new(
+            Vec4::point(-f64::INFINITY, 0.0, -f64::INFINITY),
+            Vec4::point(f64::INFINITY, 0.0, f64::INFINITY),
+        )
+    }
+
+    fn local_normal_at(&self, _local_point: Vec4, _hit: &Intersection) -> Vec4 {
         Vec4::VEC_Y_ONE
     }
     fn local_intersect(&self, local_ray: Ray) -> Intersections {
@@ -81,6 +98,7 @@ impl Plane {
             transform,
             material: material.unwrap_or_default(),
             inverse_transform,
+            parent: None,
         }
     }
 
@@ -100,6 +118,7 @@ impl Default for Plane {
             transform: Mat4::default(),
             inverse_transform: Mat4::default(),
             material: Material::default(),
+            parent: None,
         }
     }
 }
@@ -111,14 +130,15 @@ mod tests {
     #[test]
     fn normal_is_const() {
         let plane = Plane::default();
+        let hit = Intersection::new(plane.box_clone(), 0.0);
         let exp = Vec4::VEC_Y_ONE;
-        assert_eq!(plane.local_normal_at(Vec4::point(0.0, 0.0, 0.0)), exp);
+        assert_eq!(plane.local_normal_at(Vec4::point(0.0, 0.0, 0.0), &hit), exp);
         assert_eq!(
-            plane.local_normal_at(Vec4::point(10.0, 0.0, -10.0)),
+            plane.local_normal_at(Vec4::point(10.0, 0.0, -10.0), &hit),
             exp
         );
         assert_eq!(
-            plane.local_normal_at(Vec4::point(-5.0, 0.0, 150.0)),
+            plane.local_normal_at(Vec4::point(-5.0, 0.0, 150.0), &hit),
             exp
         );
     }
@@ -158,4 +178,16 @@ mod tests {
         assert_eq!(xs[0].t, 1.0);
         assert_eq!(&xs[0].object, &plane);
     }
+
+    #[test]
+    fn bounds() {
+        let plane = Plane::default();
+        let b = plane.bounds();
+        assert!(b.min.x.is_infinite() && b.min.x < 0.0);
+        assert!(b.min.z.is_infinite() && b.min.z < 0.0);
+        assert!(b.max.x.is_infinite() && b.max.x > 0.0);
+        assert!(b.max.z.is_infinite() && b.max.z > 0.0);
+        assert_eq!(b.min.y, 0.0);
+        assert_eq!(b.max.y, 0.0);
+    }
 }
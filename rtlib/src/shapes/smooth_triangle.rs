@@ -0,0 +1,194 @@
+use crate::bounds::BoundingBox;
+use crate::intersection::{Intersection, Intersections};
+use crate::material::Material;
+use crate::math::matrix::Mat4;
+use crate::math::vec4::Vec4;
+use crate::ray::Ray;
+use crate::shapes::triangle::moller_trumbore;
+use crate::shapes::{BoxShape, Parent, Shape};
+use crate::util::uid;
+
+use std::any::Any;
+use std::fmt::Debug;
+use std::sync::Arc;
+
+/// A *smooth triangle* carries a normal for each of its vertices.
+/// The surface normal is interpolated from those using the barycentric
+/// `u`/`v` of the hit, which hides the facets of a mesh.
+#[derive(Debug, Clone)]
+pub struct SmoothTriangle {
+    uid: usize,
+    pub transform: Mat4,
+    pub inverse_transform: Mat4,
+    pub material: Material,
+    parent: Option<Arc<Parent>>,
+    pub p1: Vec4,
+    pub p2: Vec4,
+    pub p3: Vec4,
+    pub n1: Vec4,
+    pub n2: Vec4,
+    pub n3: Vec4,
+    pub e1: Vec4,
+    pub e2: Vec4,
+}
+
+impl PartialEq for SmoothTriangle {
+    fn eq(&self, other: &Self) -> bool {
+        self.uid == other.uid
+            && self.transform == other.transform
+            && self.material == other.material
+    }
+}
+
+impl Shape for SmoothTriangle {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+    fn box_clone(&self) -> BoxShape {
+        Box::new((*self).clone())
+    }
+    fn box_eq(&self, other: &dyn Any) -> bool {
+        other.downcast_ref::<Self>().map_or(false, |a| self == a)
+    }
+
+    fn set_material(&mut self, material: Material) {
+        self.material = material;
+    }
+    fn get_material(&self) -> &Material {
+        &self.material
+    }
+
+    fn get_parent(&self) -> Option<&Arc<Parent>> {
+        self.parent.as_ref()
+    }
+    fn set_parent(&mut self, parent: Option<Arc<Parent>>) {
+        self.parent = parent;
+    }
+
+    fn transform(&mut self, m: &Mat4) {
+        self.transform = m * self.transform;
+    }
+    fn set_transform(&mut self, transform: Mat4) {
+        self.transform = transform;
+        self.inverse_transform = self.transform.inverse().unwrap();
+    }
+    fn transformation(&self) -> &Mat4 {
+        &self.transform
+    }
+    fn inverse_transformation(&self) -> &Mat4 {
+        &self.inverse_transform
+    }
+
+    fn bounds(&self) -> BoundingBox {
+        let mut ret = BoundingBox::default();
+        ret.add_point(&self.p1);
+        ret.add_point(&self.p2);
+        ret.add_point(&self.p3);
+        ret
+    }
+
+    fn local_normal_at(&self, _local_point: Vec4, hit: &Intersection) -> Vec4 {
+        self.n2 * hit.u + self.n3 * hit.v + self.n1 * (1.0 - hit.u - hit.v)
+    }
+
+    fn local_intersect(&self, local_ray: Ray) -> Intersections {
+        match moller_trumbore(&local_ray, &self.p1, &self.e1, &self.e2) {
+            Some((t, u, v)) => vec![Intersection::new_with_uv(self.box_clone(), t, u, v)].into(),
+            None => Intersections::new(),
+        }
+    }
+}
+
+impl SmoothTriangle {
+    pub fn new(
+        transform: Option<Mat4>,
+        material: Option<Material>,
+        points: [Vec4; 3],
+        normals: [Vec4; 3],
+    ) -> Self {
+        let transform = transform.unwrap_or_default();
+        let inverse_transform = transform.inverse().unwrap();
+        let [p1, p2, p3] = points;
+        let [n1, n2, n3] = normals;
+        Self {
+            uid: uid::fetch_uid(),
+            transform,
+            material: material.unwrap_or_default(),
+            inverse_transform,
+            parent: None,
+            p1,
+            p2,
+            p3,
+            n1,
+            n2,
+            n3,
+            e1: p2 - p1,
+            e2: p3 - p1,
+        }
+    }
+
+    pub fn new_boxed(
+        transform: Option<Mat4>,
+        material: Option<Material>,
+        points: [Vec4; 3],
+        normals: [Vec4; 3],
+    ) -> BoxShape {
+        Box::new(Self::new(transform, material, points, normals))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::math::EPSILON;
+    use crate::precompute::PreCompute;
+
+    fn default_smooth_triangle() -> SmoothTriangle {
+        SmoothTriangle::new(
+            None,
+            None,
+            [
+                Vec4::point(0.0, 1.0, 0.0),
+                Vec4::point(-1.0, 0.0, 0.0),
+                Vec4::point(1.0, 0.0, 0.0),
+            ],
+            [Vec4::VEC_Y_ONE, -Vec4::VEC_X_ONE, Vec4::VEC_X_ONE],
+        )
+    }
+
+    #[test]
+    fn basic() {
+        let t = default_smooth_triangle();
+        assert_eq!(t.p1, Vec4::point(0.0, 1.0, 0.0));
+        assert_eq!(t.n2, -Vec4::VEC_X_ONE);
+        assert_eq!(t.e1, Vec4::vec(-1.0, -1.0, 0.0));
+        assert_eq!(t.e2, Vec4::vec(1.0, -1.0, 0.0));
+    }
+
+    #[test]
+    fn intersect_stores_uv() {
+        let t = default_smooth_triangle();
+        let r = Ray::new(&Vec4::point(-0.2, 0.3, -2.0), &Vec4::VEC_Z_ONE);
+        let xs = t.local_intersect(r);
+        assert_eq!(xs.len(), 1);
+        assert!((xs[0].u - 0.45).abs() < EPSILON);
+        assert!((xs[0].v - 0.25).abs() < EPSILON);
+    }
+
+    #[test]
+    fn normal_interpolated() {
+        let t = default_smooth_triangle();
+        let hit = Intersection::new_with_uv(t.box_clone(), 1.0, 0.45, 0.25);
+        let n = t.normal_at(Vec4::POINT_ZERO, &hit);
+        assert_eq!(n, Vec4::vec(-0.5547, 0.83205, 0.0));
+    }
+
+    #[test]
+    fn precompute_uses_uv() {
+        let t = default_smooth_triangle().box_clone();
+        let hit = Intersection::new_with_uv(t, 1.0, 0.45, 0.25);
+        let r = Ray::new(&Vec4::point(-0.2, 0.3, -2.0), &Vec4::VEC_Z_ONE);
+        let comps = PreCompute::new(&hit, &r, None);
+        assert_eq!(*comps.get_normal(), Vec4::vec(-0.5547, 0.83205, 0.0));
+    }
+}
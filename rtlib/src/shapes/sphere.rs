@@ -1,13 +1,15 @@
+use crate::bounds::BoundingBox;
 use crate::intersection::{Intersection, Intersections};
 use crate::material::Material;
 use crate::math::matrix::Mat4;
 use crate::math::vec4::Vec4;
 use crate::ray::Ray;
-use crate::shapes::{BoxShape, Shape};
+use crate::shapes::{BoxShape, Parent, Shape};
 use crate::util::uid;
 
 use std::any::Any;
 use std::fmt::Debug;
+use std::sync::Arc;
 
 #[derive(Debug, Clone)]
 pub struct Sphere {
@@ -15,6 +17,7 @@ pub struct Sphere {
     pub transform: Mat4,
     pub inverse_transform: Mat4,
     pub material: Material,
+    parent: Option<Arc<Parent>>,
 }
 
 impl PartialEq for Sphere {
@@ -43,6 +46,13 @@ impl Shape for Sphere {
         &self.material
     }
 
+    fn get_parent(&self) -> Option<&Arc<Parent>> {
+        self.parent.as_ref()
+    }
+    fn set_parent(&mut self, parent: Option<Arc<Parent>>) {
+        self.parent = parent;
+    }
+
     fn transform(&mut self, m: &Mat4) {
         self.transform = m * self.transform;
     }
@@ -57,7 +67,11 @@ impl Shape for Sphere {
         &self.inverse_transform
     }
 
-    fn local_normal_at(&self, local_point: Vec4) -> Vec4 {
+    fn bounds(&self) -> BoundingBox {
+        BoundingBox::new(Vec4::point(-1.0, -1.0, -1.0), Vec4::point(1.0, 1.0, 1.0))
+    }
+
+    fn local_normal_at(&self, local_point: Vec4, _hit: &Intersection) -> Vec4 {
         local_point - Vec4::POINT_ZERO
     }
     fn local_intersect(&self, local_ray: Ray) -> Intersections {
@@ -93,6 +107,7 @@ impl Sphere {
             transform,
             material: material.unwrap_or_default(),
             inverse_transform,
+            parent: None,
         }
     }
 
@@ -112,6 +127,7 @@ impl Default for Sphere {
             transform: Mat4::default(),
             inverse_transform: Mat4::default(),
             material: Material::default(),
+            parent: None,
         }
     }
 }
@@ -194,29 +210,31 @@ mod tests {
     #[test]
     fn normal_at() {
         let s = Sphere::default();
+        let hit = Intersection::new(s.box_clone(), 0.0);
         let p = Vec4::point(1.0, 0.0, 0.0);
         let exp = Vec4::vec(1.0, 0.0, 0.0);
-        assert_eq!(exp, s.normal_at(p));
+        assert_eq!(exp, s.normal_at(p, &hit));
 
         let p = Vec4::point(0.0, 1.0, 0.0);
         let exp = Vec4::vec(0.0, 1.0, 0.0);
-        assert_eq!(exp, s.normal_at(p));
+        assert_eq!(exp, s.normal_at(p, &hit));
 
         let p = Vec4::point(0.0, 0.0, 1.0);
         let exp = Vec4::vec(0.0, 0.0, 1.0);
-        assert_eq!(exp, s.normal_at(p));
+        assert_eq!(exp, s.normal_at(p, &hit));
 
         let p = Vec4::point(3f64.sqrt() / 3.0, 3f64.sqrt() / 3.0, 3f64.sqrt() / 3.0);
         let exp = Vec4::vec(3f64.sqrt() / 3.0, 3f64.sqrt() / 3.0, 3f64.sqrt() / 3.0);
-        let n = s.normal_at(p);
+        let n = s.normal_at(p, &hit);
         assert_eq!(exp, n);
         assert_eq!(n, n.normalize());
 
         let s = Sphere::new(Some(Mat4::translation(0.0, 1.0, 0.0)), None);
+        let hit = Intersection::new(s.box_clone(), 0.0);
 
         let p = Vec4::point(0.0, 1.70711, -0.70711);
         let exp = Vec4::vec(0.0, 0.70711, -0.70711);
-        let n = s.normal_at(p);
+        let n = s.normal_at(p, &hit);
         assert_eq!(exp, n);
     }
 
@@ -226,9 +244,18 @@ mod tests {
             Some(Mat4::scaling(1.0, 0.5, 1.0) * Mat4::rotation_z(PI / 5.0)),
             None,
         );
+        let hit = Intersection::new(s.box_clone(), 0.0);
         let p = Vec4::point(0.0, SQRT_2 / 2.0, -SQRT_2 / 2.0);
         let exp = Vec4::vec(0.0, 0.97014, -0.24254);
-        let n = s.normal_at(p);
+        let n = s.normal_at(p, &hit);
         assert_eq!(exp, n);
     }
+
+    #[test]
+    fn bounds() {
+        let s = Sphere::default();
+        let b = s.bounds();
+        assert_eq!(b.min, Vec4::point(-1.0, -1.0, -1.0));
+        assert_eq!(b.max, Vec4::point(1.0, 1.0, 1.0));
+    }
 }
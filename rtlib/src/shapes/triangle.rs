@@ -0,0 +1,306 @@
+use crate::bounds::BoundingBox;
+use crate::intersection::{Intersection, Intersections};
+use crate::material::Material;
+use crate::math::matrix::Mat4;
+use crate::math::vec4::Vec4;
+use crate::math::EPSILON;
+use crate::ray::Ray;
+use crate::shapes::{BoxShape, Parent, Shape};
+use crate::util::uid;
+
+use std::any::Any;
+use std::fmt::Debug;
+use std::sync::Arc;
+
+/// A flat *triangle* defined by three points.
+/// Edge vectors and the face normal are precomputed on construction.
+/// Triangles with no area have a zero normal and are never hit.
+#[derive(Debug, Clone)]
+pub struct Triangle {
+    uid: usize,
+    pub transform: Mat4,
+    pub inverse_transform: Mat4,
+    pub material: Material,
+    parent: Option<Arc<Parent>>,
+    pub p1: Vec4,
+    pub p2: Vec4,
+    pub p3: Vec4,
+    pub e1: Vec4,
+    pub e2: Vec4,
+    pub normal: Vec4,
+}
+
+impl PartialEq for Triangle {
+    fn eq(&self, other: &Self) -> bool {
+        self.uid == other.uid
+            && self.transform == other.transform
+            && self.material == other.material
+    }
+}
+
+impl Shape for Triangle {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+    fn box_clone(&self) -> BoxShape {
+        Box::new((*self).clone())
+    }
+    fn box_eq(&self, other: &dyn Any) -> bool {
+        other.downcast_ref::<Self>().map_or(false, |a| self == a)
+    }
+
+    fn set_material(&mut self, material: Material) {
+        self.material = material;
+    }
+    fn get_material(&self) -> &Material {
+        &self.material
+    }
+
+    fn get_parent(&self) -> Option<&Arc<Parent>> {
+        self.parent.as_ref()
+    }
+    fn set_parent(&mut self, parent: Option<Arc<Parent>>) {
+        self.parent = parent;
+    }
+
+    fn transform(&mut self, m: &Mat4) {
+        self.transform = m * self.transform;
+    }
+    fn set_transform(&mut self, transform: Mat4) {
+        self.transform = transform;
+        self.inverse_transform = self.transform.inverse().unwrap();
+    }
+    fn transformation(&self) -> &Mat4 {
+        &self.transform
+    }
+    fn inverse_transformation(&self) -> &Mat4 {
+        &self.inverse_transform
+    }
+
+    fn bounds(&self) -> BoundingBox {
+        let mut ret = BoundingBox::default();
+        ret.add_point(&self.p1);
+        ret.add_point(&self.p2);
+        ret.add_point(&self.p3);
+        ret
+    }
+
+    fn local_normal_at(&self, _local_point: Vec4, _hit: &Intersection) -> Vec4 {
+        self.normal
+    }
+
+    fn local_intersect(&self, local_ray: Ray) -> Intersections {
+        if self.is_degenerate() {
+            return Intersections::new();
+        }
+        match moller_trumbore(&local_ray, &self.p1, &self.e1, &self.e2) {
+            Some((t, u, v)) => vec![Intersection::new_with_uv(self.box_clone(), t, u, v)].into(),
+            None => Intersections::new(),
+        }
+    }
+}
+
+/// Möller–Trumbore ray-triangle intersection.
+/// Returns `t` and the barycentric `u`/`v` of the hit, if any.
+pub(crate) fn moller_trumbore(
+    ray: &Ray,
+    p1: &Vec4,
+    e1: &Vec4,
+    e2: &Vec4,
+) -> Option<(f64, f64, f64)> {
+    let dir_cross_e2 = ray.direction.cross(e2);
+    let det = e1.dot(&dir_cross_e2);
+    // ray is parallel to the triangle's plane
+    if det.abs() < EPSILON {
+        return None;
+    }
+
+    let f = 1.0 / det;
+    let p1_to_origin = ray.origin - p1;
+    let u = f * p1_to_origin.dot(&dir_cross_e2);
+    if !(0.0..=1.0).contains(&u) {
+        return None;
+    }
+
+    let origin_cross_e1 = p1_to_origin.cross(e1);
+    let v = f * ray.direction.dot(&origin_cross_e1);
+    if v < 0.0 || u + v > 1.0 {
+        return None;
+    }
+
+    Some((f * e2.dot(&origin_cross_e1), u, v))
+}
+
+impl Triangle {
+    pub fn new(
+        transform: Option<Mat4>,
+        material: Option<Material>,
+        p1: Vec4,
+        p2: Vec4,
+        p3: Vec4,
+    ) -> Self {
+        let transform = transform.unwrap_or_default();
+        let inverse_transform = transform.inverse().unwrap();
+        let e1 = p2 - p1;
+        let e2 = p3 - p1;
+        let cross = e2.cross(&e1);
+        let normal = if cross.magnitude() == 0.0 {
+            Vec4::ZERO
+        } else {
+            cross.normalize()
+        };
+        Self {
+            uid: uid::fetch_uid(),
+            transform,
+            material: material.unwrap_or_default(),
+            inverse_transform,
+            parent: None,
+            p1,
+            p2,
+            p3,
+            e1,
+            e2,
+            normal,
+        }
+    }
+
+    pub fn new_boxed(
+        transform: Option<Mat4>,
+        material: Option<Material>,
+        p1: Vec4,
+        p2: Vec4,
+        p3: Vec4,
+    ) -> BoxShape {
+        Box::new(Self::new(transform, material, p1, p2, p3))
+    }
+
+    /// Whether the points are on a line, leaving no area to hit
+    pub fn is_degenerate(&self) -> bool {
+        self.normal.magnitude() == 0.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use itertools::izip;
+
+    fn default_triangle() -> Triangle {
+        Triangle::new(
+            None,
+            None,
+            Vec4::point(0.0, 1.0, 0.0),
+            Vec4::point(-1.0, 0.0, 0.0),
+            Vec4::point(1.0, 0.0, 0.0),
+        )
+    }
+
+    #[test]
+    fn basic() {
+        let t = default_triangle();
+        assert_eq!(t.transform, Mat4::IDENTITY);
+        assert_eq!(t.material, Material::default());
+        assert_eq!(t.e1, Vec4::vec(-1.0, -1.0, 0.0));
+        assert_eq!(t.e2, Vec4::vec(1.0, -1.0, 0.0));
+        assert_eq!(t.normal, Vec4::vec(0.0, 0.0, -1.0));
+    }
+
+    #[test]
+    fn degenerate() {
+        assert!(!default_triangle().is_degenerate());
+        let points = [
+            [
+                Vec4::point(0.0, 0.0, 0.0),
+                Vec4::point(1.0, 0.0, 0.0),
+                Vec4::point(2.0, 0.0, 0.0),
+            ],
+            [
+                Vec4::point(0.0, 1.0, 0.0),
+                Vec4::point(0.0, 1.0, 0.0),
+                Vec4::point(1.0, 0.0, 0.0),
+            ],
+        ];
+        for [p1, p2, p3] in points.iter() {
+            let t = Triangle::new(None, None, *p1, *p2, *p3);
+            assert!(t.is_degenerate());
+            assert_eq!(t.normal, Vec4::ZERO);
+            let r = Ray::new(&Vec4::point(0.5, 0.0, -2.0), &Vec4::VEC_Z_ONE);
+            assert!(t.local_intersect(r).is_empty());
+        }
+    }
+
+    #[test]
+    fn normal_is_const() {
+        let t = default_triangle();
+        let points = [
+            Vec4::point(0.0, 0.5, 0.0),
+            Vec4::point(-0.5, 0.75, 0.0),
+            Vec4::point(0.5, 0.25, 0.0),
+        ];
+        let hit = Intersection::new(t.box_clone(), 0.0);
+        for p in points.iter() {
+            assert_eq!(t.local_normal_at(*p, &hit), t.normal);
+        }
+    }
+
+    #[test]
+    fn intersect_parallel() {
+        let t = default_triangle();
+        let r = Ray::new(&Vec4::point(0.0, -1.0, -2.0), &Vec4::VEC_Y_ONE);
+        let xs = t.local_intersect(r);
+        assert!(xs.is_empty());
+    }
+
+    #[test]
+    fn ray_miss_edges() {
+        let t = default_triangle();
+        // misses p1-p3 edge, p1-p2 edge and p2-p3 edge
+        let orgs = [
+            Vec4::point(1.0, 1.0, -2.0),
+            Vec4::point(-1.0, 1.0, -2.0),
+            Vec4::point(0.0, -1.0, -2.0),
+        ];
+        for o in orgs.iter() {
+            let r = Ray::new(o, &Vec4::VEC_Z_ONE);
+            let xs = t.local_intersect(r);
+            assert!(xs.is_empty(), "Failed for {:?}", o);
+        }
+    }
+
+    #[test]
+    fn ray_intersect() {
+        let t = default_triangle();
+        let orgs = [Vec4::point(0.0, 0.5, -2.0), Vec4::point(0.25, 0.25, -3.0)];
+        let exps = [2.0, 3.0];
+        for (o, et) in izip!(&orgs, &exps) {
+            let r = Ray::new(o, &Vec4::VEC_Z_ONE);
+            let xs = t.local_intersect(r);
+            assert_eq!(xs.len(), 1);
+            assert_eq!(xs[0].t, *et);
+        }
+    }
+
+    #[test]
+    fn intersect_stores_uv() {
+        let t = default_triangle();
+        let r = Ray::new(&Vec4::point(-0.2, 0.3, -2.0), &Vec4::VEC_Z_ONE);
+        let xs = t.local_intersect(r);
+        assert_eq!(xs.len(), 1);
+        assert!((xs[0].u - 0.45).abs() < EPSILON);
+        assert!((xs[0].v - 0.25).abs() < EPSILON);
+    }
+
+    #[test]
+    fn bounds() {
+        let t = Triangle::new(
+            None,
+            None,
+            Vec4::point(-3.0, 7.0, 2.0),
+            Vec4::point(6.0, 2.0, -4.0),
+            Vec4::point(2.0, -1.0, -1.0),
+        );
+        let b = t.bounds();
+        assert_eq!(b.min, Vec4::point(-3.0, -1.0, -4.0));
+        assert_eq!(b.max, Vec4::point(6.0, 7.0, 2.0));
+    }
+}
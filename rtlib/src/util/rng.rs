@@ -0,0 +1,80 @@
+//! Small deterministic random number generator (SplitMix64).
+//! Renders seed it per pixel, so the output doesn't depend on
+//! the order or the thread pixels are rendered in.
+
+use crate::math::vec4::Vec4;
+
+#[derive(Debug, Clone)]
+pub struct Rng {
+    state: u64,
+}
+
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        Self { state: seed }
+    }
+
+    /// Generator seeded from pixel coordinates
+    pub fn for_pixel(x: u32, y: u32) -> Self {
+        Self::new(((y as u64) << 32) | x as u64)
+    }
+
+    /// Generator seeded from a point, so that everything sampled at the
+    /// same point (e.g. while shading it) gets the same values
+    pub fn for_point(p: &Vec4) -> Self {
+        Self::new(p.x.to_bits() ^ p.y.to_bits().rotate_left(21) ^ p.z.to_bits().rotate_left(42))
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    /// Uniform value in `[0, 1)`
+    pub fn next_f64(&mut self) -> f64 {
+        // top 53 bits fill the mantissa exactly
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn deterministic() {
+        let mut a = Rng::for_pixel(3, 7);
+        let mut b = Rng::for_pixel(3, 7);
+        let mut c = Rng::for_pixel(7, 3);
+        for _ in 0..100 {
+            let v = a.next_u64();
+            assert_eq!(v, b.next_u64());
+            assert_ne!(v, c.next_u64());
+        }
+
+        let p = Vec4::point(0.5, -1.0, 2.0);
+        let mut a = Rng::for_point(&p);
+        let mut b = Rng::for_point(&p);
+        let mut c = Rng::for_point(&Vec4::point(2.0, -1.0, 0.5));
+        for _ in 0..100 {
+            let v = a.next_u64();
+            assert_eq!(v, b.next_u64());
+            assert_ne!(v, c.next_u64());
+        }
+    }
+
+    #[test]
+    fn f64_range() {
+        let mut rng = Rng::new(42);
+        let mut sum = 0.0;
+        for _ in 0..10000 {
+            let v = rng.next_f64();
+            assert!((0.0..1.0).contains(&v));
+            sum += v;
+        }
+        assert!((sum / 10000.0 - 0.5).abs() < 0.02);
+    }
+}